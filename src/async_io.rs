@@ -3,70 +3,130 @@
 
 extern crate async_std;
 
-use crate::response::{readline, Response, ResponseLine, ResponseValue};
+use crate::cluster::{command_key, key_slot, ClusterNodes, Redirect, MAX_REDIRECTS};
+use crate::connection::ConnectionBuilder;
+use crate::modifiers::{Arity, Insertion, Side};
+use crate::response::{
+  command_arity, command_present, float_value, published_message, readline, transaction_value, ArrayAccumulator,
+  Message, Response, ResponseLine, ResponseValue,
+};
+use crate::{Command, HashCommand, ListCommand, StringCommand};
 
 use async_std::net::TcpStream;
 use async_std::prelude::*;
 
 use std::io::{Error, ErrorKind};
 
-/// Attempts to read RESP standard messages (newline delimeters), parsing into our `ResponseValue`
-/// enum.
-pub async fn read<C>(connection: C) -> Result<Response, Error>
+/// The amount of items requested by each pop while draining a list.
+const DRAIN_BATCH: usize = 1000;
+
+/// Parses a single response from a buffered reader. Keeping the buffer outside of this function
+/// allows pipelined exchanges to read many responses without losing whatever was buffered beyond
+/// the first one.
+async fn read_buffered<B>(reader: &mut B) -> Result<Response, Error>
 where
-  C: async_std::io::Read + std::marker::Unpin,
+  B: async_std::io::BufRead + std::marker::Unpin,
 {
-  let mut reader = async_std::io::BufReader::new(connection);
-  let mut buffer = String::new();
+  read_tagged_buffered(reader).await.map(|(_, response)| response)
+}
 
-  match reader.read_line(&mut buffer).await.and_then(|_res| readline(buffer)) {
-    Ok(ResponseLine::Array(size)) => {
-      let mut store = Vec::with_capacity(size);
+/// Parses a single response, along with the type tag of the first line the server sent.
+async fn read_tagged_buffered<B>(reader: &mut B) -> Result<(&'static str, Response), Error>
+where
+  B: async_std::io::BufRead + std::marker::Unpin,
+{
+  let mut buffer = String::new();
+  let line = reader.read_line(&mut buffer).await.and_then(|_res| readline(buffer))?;
+  let tag = line.type_tag();
 
+  let response = match line {
+    ResponseLine::Array(size) => {
       if size == 0 {
-        return Ok(Response::Array(vec![]));
+        return Ok((tag, Response::Array(vec![])));
       }
 
-      while store.len() < size {
+      let mut accumulator = ArrayAccumulator::new(size);
+      let mut failure = None;
+
+      loop {
         let mut line_buffer = String::new();
 
-        let kind = reader
+        let kind = match reader
           .read_line(&mut line_buffer)
           .await
-          .and_then(|_res| readline(line_buffer))?;
+          .and_then(|_res| readline(line_buffer))
+        {
+          Ok(kind) => kind,
+          Err(_) => break,
+        };
 
-        match kind {
+        let complete = match kind {
           ResponseLine::BulkString(size) => {
             let mut real_value = String::with_capacity(size);
             reader.read_line(&mut real_value).await?;
-            store.push(ResponseValue::String(real_value.trim_end().to_string()));
+            accumulator.push(ResponseValue::String(real_value.trim_end().to_string()))
           }
-          _ => break,
-        }
+          ResponseLine::SimpleString(simple) => accumulator.push(ResponseValue::String(simple.trim_end().to_string())),
+          ResponseLine::Integer(value) => accumulator.push(ResponseValue::Integer(value)),
+          ResponseLine::Null => accumulator.push(ResponseValue::Empty),
+          ResponseLine::Array(inner) => accumulator.open(inner),
+          // The remaining elements are still read to leave the connection usable; the first error
+          // is returned once the array is complete.
+          ResponseLine::Error(message) => {
+            failure.get_or_insert(message);
+            accumulator.push(ResponseValue::Empty)
+          }
+        };
 
-        if store.len() >= size {
-          return Ok(Response::Array(store));
+        if let Some(store) = complete {
+          return match failure {
+            Some(message) => Err(Error::other(message)),
+            None => Ok((tag, Response::Array(store))),
+          };
         }
       }
 
-      Ok(Response::Array(store))
+      let message = format!(
+        "expected {} elements in response and received {}",
+        size,
+        accumulator.received()
+      );
+      Err(Error::new(ErrorKind::InvalidData, message))
     }
-    Ok(ResponseLine::BulkString(size)) => {
-      if size < 1 {
-        return Ok(Response::Item(ResponseValue::Empty));
-      }
-
+    ResponseLine::BulkString(size) => {
       let mut real_value = String::with_capacity(size);
       reader.read_line(&mut real_value).await?;
 
       Ok(Response::Item(ResponseValue::String(real_value.trim_end().to_string())))
     }
-    Ok(ResponseLine::Null) => Ok(Response::Item(ResponseValue::Empty)),
-    Ok(ResponseLine::SimpleString(simple)) => Ok(Response::Item(ResponseValue::String(simple.trim_end().to_string()))),
-    Ok(ResponseLine::Integer(value)) => Ok(Response::Item(ResponseValue::Integer(value))),
-    Ok(ResponseLine::Error(e)) => Err(Error::new(ErrorKind::Other, e)),
-    Err(e) => Err(e),
-  }
+    ResponseLine::Null => Ok(Response::Item(ResponseValue::Empty)),
+    ResponseLine::SimpleString(simple) => Ok(Response::Item(ResponseValue::String(simple.trim_end().to_string()))),
+    ResponseLine::Integer(value) => Ok(Response::Item(ResponseValue::Integer(value))),
+    ResponseLine::Error(e) => Err(Error::other(e)),
+  };
+
+  response.map(|response| (tag, response))
+}
+
+/// Attempts to read RESP standard messages (newline delimeters), parsing into our `ResponseValue`
+/// enum.
+pub async fn read<C>(connection: C) -> Result<Response, Error>
+where
+  C: async_std::io::Read + std::marker::Unpin,
+{
+  let mut reader = async_std::io::BufReader::new(connection);
+  read_buffered(&mut reader).await
+}
+
+/// Reads a response along with the type tag of the first line the server sent (see
+/// `ResponseLine::type_tag`). Unlike the parsed `Response`, the tag distinguishes simple string
+/// replies such as `+OK` from bulk string replies.
+pub async fn read_tagged<C>(connection: C) -> Result<(&'static str, Response), Error>
+where
+  C: async_std::io::Read + std::marker::Unpin,
+{
+  let mut reader = async_std::io::BufReader::new(connection);
+  read_tagged_buffered(&mut reader).await
 }
 
 /// An async implementation of a complete message exchange. The provided message will be written to
@@ -88,3 +148,262 @@ where
   let mut stream = TcpStream::connect(addr).await?;
   execute(&mut stream, message).await
 }
+
+impl ConnectionBuilder {
+  /// Opens a connection to the address with the configured socket options applied.
+  pub async fn connect(&self) -> Result<TcpStream, Error> {
+    let stream = TcpStream::connect(self.addr.as_str()).await?;
+    self.configure(socket2::SockRef::from(&stream))?;
+    Ok(stream)
+  }
+}
+
+/// Sets every field of a hash by splitting the fields into `HSET` commands of at most `chunk`
+/// fields each. The commands are pipelined; all of them are written before any response is read.
+/// The returned value is the total amount of fields that were newly added to the hash.
+pub async fn hset_all<C>(mut connection: C, key: &str, fields: &[(&str, &str)], chunk: usize) -> Result<i64, Error>
+where
+  C: async_std::io::Write + std::marker::Unpin + async_std::io::Read,
+{
+  if chunk == 0 {
+    return Err(Error::new(
+      ErrorKind::InvalidInput,
+      "kramer: hset_all chunk size must be non-zero",
+    ));
+  }
+
+  let commands = fields
+    .chunks(chunk)
+    .map(|pairs| {
+      format!(
+        "{}",
+        HashCommand::Set(key, Arity::Many(pairs.to_vec()), Insertion::Always)
+      )
+    })
+    .collect::<Vec<String>>();
+
+  connection.write_all(commands.concat().as_bytes()).await?;
+
+  let mut reader = async_std::io::BufReader::new(connection);
+  let mut outcome = Ok(0);
+
+  // Every response must be consumed, even after a failure, to leave the connection in a usable state.
+  for _ in 0..commands.len() {
+    let response = read_buffered(&mut reader).await;
+
+    outcome = match (outcome, response) {
+      (Ok(total), Ok(Response::Item(ResponseValue::Integer(added)))) => Ok(total + added),
+      (Ok(_), Ok(other)) => Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("kramer: unexpected HSET response {:?}", other),
+      )),
+      (Ok(_), Err(e)) => Err(e),
+      (Err(e), _) => Err(e),
+    };
+  }
+
+  outcome
+}
+
+/// Subscribes the connection to the provided channels and invokes the handler with every message
+/// published to them. The returned future resolves once the handler returns `ControlFlow::Break`,
+/// with its value. Note that the connection is _not_ unsubscribed before returning.
+pub async fn subscribe_with<C, S, F, B>(mut connection: C, channels: Arity<S>, mut handler: F) -> Result<B, Error>
+where
+  S: std::fmt::Display,
+  C: async_std::io::Write + std::marker::Unpin + async_std::io::Read,
+  F: FnMut(Message) -> std::ops::ControlFlow<B>,
+{
+  let subscribe = Command::Subscribe::<S, &str>(channels);
+  connection.write_all(format!("{}", subscribe).as_bytes()).await?;
+
+  let mut reader = async_std::io::BufReader::new(connection);
+
+  loop {
+    if let Some(message) = published_message(read_buffered(&mut reader).await?) {
+      if let std::ops::ControlFlow::Break(value) = handler(message) {
+        return Ok(value);
+      }
+    }
+  }
+}
+
+/// Increments the value stored at a key by a floating point amount, returning the new value.
+pub async fn incr_float<C, S>(connection: C, key: S, by: f64) -> Result<f64, Error>
+where
+  S: std::fmt::Display,
+  C: async_std::io::Write + std::marker::Unpin + async_std::io::Read,
+{
+  float_value(execute(connection, StringCommand::IncrFloat::<S, &str>(key, by)).await?)
+}
+
+/// Increments a field of the hash stored at a key by a floating point amount, returning the new
+/// value.
+pub async fn hincr_float<C, S>(connection: C, key: S, field: S, by: f64) -> Result<f64, Error>
+where
+  S: std::fmt::Display,
+  C: async_std::io::Write + std::marker::Unpin + async_std::io::Read,
+{
+  float_value(execute(connection, HashCommand::IncrFloat::<S, &str>(key, field, by)).await?)
+}
+
+/// Returns the value stored at a key while updating its time to live. Servers that support `GETEX`
+/// (6.2 and later) receive that single command, while older servers receive a `GET` and expiry
+/// within a transaction. Support is determined by a `COMMAND INFO` lookup whose result is stored
+/// in `getex_support`; providing the same value to later calls will skip the lookup.
+/// Time to lives shorter than a millisecond are rejected with `ErrorKind::InvalidInput`.
+///
+/// Commands queued in a transaction are applied independently, so when the `GET` of the fallback
+/// fails (for example, because the key does not hold a string) the error is returned but the
+/// expiry has still been applied. `GETEX` leaves the time to live untouched in that case.
+pub async fn get_and_extend<C, S>(
+  mut connection: C,
+  key: S,
+  ttl: std::time::Duration,
+  getex_support: &mut Option<bool>,
+) -> Result<Response, Error>
+where
+  S: std::fmt::Display,
+  C: async_std::io::Write + std::marker::Unpin + async_std::io::Read,
+{
+  // Neither `GETEX key PX 0` nor `PEXPIRE key 0` leave the key in place; reject these up front so
+  // both paths behave the same.
+  if ttl.as_millis() == 0 {
+    return Err(Error::new(
+      ErrorKind::InvalidInput,
+      "kramer: get_and_extend ttl must be at least one millisecond",
+    ));
+  }
+
+  let supported = match getex_support {
+    Some(supported) => *supported,
+    None => {
+      let info = execute(&mut connection, Command::CommandInfo::<_, &str>(Arity::One("GETEX"))).await?;
+      *getex_support.insert(command_present(&info))
+    }
+  };
+
+  if supported {
+    return execute(connection, StringCommand::GetEx::<_, &str>(key, Some(ttl))).await;
+  }
+
+  let transaction = format!(
+    "{}{}{}{}",
+    Command::Multi::<&str, &str>,
+    StringCommand::Get::<_, &str>(Arity::One(&key)),
+    Command::Expire::<_, &str>(&key, ttl, None),
+    Command::Exec::<&str, &str>
+  );
+  connection.write_all(transaction.as_bytes()).await?;
+
+  let mut reader = async_std::io::BufReader::new(connection);
+
+  // `MULTI` and each queued command are acknowledged before `EXEC` returns the actual replies.
+  let mut acknowledgements = Vec::with_capacity(3);
+  for _ in 0..3 {
+    acknowledgements.push(read_buffered(&mut reader).await);
+  }
+  let replies = read_buffered(&mut reader).await;
+
+  for acknowledgement in acknowledgements {
+    acknowledgement?;
+  }
+
+  transaction_value(replies?)
+}
+
+/// Sends a command to the cluster node serving the slot of its key. When that node replies with a
+/// `MOVED` redirect, the slot map is updated and the command is retried on the indicated node, up
+/// to `MAX_REDIRECTS` times. Commands without a key are sent to the node serving slot `0`.
+///
+/// Every attempt opens a new connection to the node; connections are not kept between calls.
+/// Slots that have not yet been learned from a redirect are sent to the first node, so the first
+/// command for each slot may take an extra round trip.
+pub async fn execute_cluster<S>(nodes: &mut ClusterNodes, message: S) -> Result<Response, Error>
+where
+  S: std::fmt::Display,
+{
+  let message = format!("{}", message);
+  let slot = command_key(&message).map(|key| key_slot(&key)).unwrap_or_default();
+  let mut redirects = 0;
+
+  loop {
+    let addr = nodes
+      .node_for(slot)
+      .map(String::from)
+      .ok_or_else(|| Error::new(ErrorKind::NotFound, "kramer: no cluster nodes available"))?;
+
+    match send(addr.as_str(), message.as_str()).await {
+      Err(error) if redirects < MAX_REDIRECTS => match Redirect::parse(&error.to_string()) {
+        Some(redirect) => {
+          nodes.assign(redirect);
+          redirects += 1;
+        }
+        None => return Err(error),
+      },
+      result => return result,
+    }
+  }
+}
+
+/// Pops every item from the side of a list until it is empty, returning the items in the order
+/// they were popped. Items are popped many at a time where supported (redis 6.2 and later); older
+/// servers fall back to popping a single item at a time. Support is determined by a `COMMAND INFO`
+/// lookup whose result is stored in `counted_pops`; providing the same value to later calls will
+/// skip the lookup.
+pub async fn drain_list<C, S>(
+  mut connection: C,
+  key: S,
+  side: Side,
+  counted_pops: &mut Option<bool>,
+) -> Result<Vec<String>, Error>
+where
+  S: std::fmt::Display,
+  C: async_std::io::Write + std::marker::Unpin + async_std::io::Read,
+{
+  let counted = match counted_pops {
+    Some(counted) => *counted,
+    None => {
+      let name = match side {
+        Side::Left => "LPOP",
+        Side::Right => "RPOP",
+      };
+      let info = execute(&mut connection, Command::CommandInfo::<_, &str>(Arity::One(name))).await?;
+      // Accepting a count changed the arity of the pop commands from 2 to -2.
+      *counted_pops.insert(command_arity(&info).is_some_and(|arity| arity < 0))
+    }
+  };
+  let mut drained = vec![];
+
+  loop {
+    let response = if counted {
+      execute(
+        &mut connection,
+        ListCommand::PopMany::<_, &str>(side.clone(), &key, DRAIN_BATCH),
+      )
+      .await?
+    } else {
+      execute(&mut connection, ListCommand::Pop::<_, &str>(side.clone(), &key, None)).await?
+    };
+
+    match response {
+      Response::Item(ResponseValue::Empty) => return Ok(drained),
+      Response::Item(ResponseValue::String(item)) => drained.push(item),
+      Response::Array(items) => {
+        for item in items {
+          match item {
+            ResponseValue::String(item) => drained.push(item),
+            other => {
+              let message = format!("kramer: unexpected list item {:?}", other);
+              return Err(Error::new(ErrorKind::InvalidData, message));
+            }
+          }
+        }
+      }
+      other => {
+        let message = format!("kramer: unexpected pop response {:?}", other);
+        return Err(Error::new(ErrorKind::InvalidData, message));
+      }
+    }
+  }
+}
@@ -0,0 +1,190 @@
+/// Redis cluster distributes keys across this many hash slots.
+pub const SLOT_COUNT: u16 = 16384;
+
+/// The amount of `MOVED` redirects a command will follow before the error is returned.
+pub const MAX_REDIRECTS: usize = 5;
+
+/// The CRC16 variant (XMODEM) used by redis cluster to hash keys into slots.
+fn crc16(bytes: &[u8]) -> u16 {
+  bytes.iter().fold(0u16, |crc, byte| {
+    (0..8).fold(crc ^ (u16::from(*byte) << 8), |crc, _| {
+      if crc & 0x8000 != 0 {
+        (crc << 1) ^ 0x1021
+      } else {
+        crc << 1
+      }
+    })
+  })
+}
+
+/// Returns the hash slot a key belongs to. When the key contains a non-empty hash tag (the content
+/// between the first `{` and the following `}`), only the tag is hashed.
+pub fn key_slot(key: &str) -> u16 {
+  let hashed = match key.find('{') {
+    Some(open) => match key[open + 1..].find('}') {
+      Some(0) | None => key,
+      Some(close) => &key[open + 1..open + 1 + close],
+    },
+    None => key,
+  };
+
+  crc16(hashed.as_bytes()) % SLOT_COUNT
+}
+
+/// A `MOVED` error is returned by cluster nodes when a command is sent for a slot that is served by
+/// a different node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+  /// The slot of the key the command was sent for.
+  pub slot: u16,
+
+  /// The address of the node serving the slot.
+  pub addr: String,
+}
+
+impl Redirect {
+  /// Attempts to parse a redirect from an error message, e.g. `-MOVED 3999 127.0.0.1:6381`.
+  pub fn parse(message: &str) -> Option<Self> {
+    let mut parts = message.trim().trim_start_matches('-').split_whitespace();
+
+    match (parts.next(), parts.next(), parts.next()) {
+      (Some("MOVED"), Some(slot), Some(addr)) => slot.parse::<u16>().ok().map(|slot| Redirect {
+        slot,
+        addr: addr.to_string(),
+      }),
+      _ => None,
+    }
+  }
+}
+
+/// Returns the key a formatted command operates on, which determines the node it is routed to.
+/// Most commands take their key as the first argument; commands without keys return `None`.
+pub fn command_key(message: &str) -> Option<String> {
+  // Every other line of a formatted command is a bulk string length header.
+  let args = message.split("\r\n").skip(2).step_by(2).collect::<Vec<&str>>();
+
+  let key = match args.first().map(|name| name.to_ascii_uppercase()).as_deref() {
+    None | Some("ACL" | "AUTH" | "COMMAND" | "ECHO" | "EXEC" | "KEYS" | "MULTI" | "PUBLISH" | "SUBSCRIBE") => None,
+    Some("XGROUP") => args.get(2).copied(),
+    Some("XREADGROUP") => xreadgroup_key(&args),
+    Some(_) => args.get(1).copied(),
+  };
+
+  key.map(|key| key.to_string())
+}
+
+/// `XREADGROUP GROUP <group> <consumer>` is followed by its options and then `STREAMS <key>`. The
+/// key is found by walking the options rather than searching for `STREAMS`, which may also be the
+/// name of the group or consumer.
+fn xreadgroup_key<'a>(args: &[&'a str]) -> Option<&'a str> {
+  let mut index = 4;
+
+  loop {
+    match args.get(index).map(|arg| arg.to_ascii_uppercase()).as_deref() {
+      Some("COUNT" | "BLOCK") => index += 2,
+      Some("NOACK") => index += 1,
+      Some("STREAMS") => return args.get(index + 1).copied(),
+      _ => return None,
+    }
+  }
+}
+
+/// Keeps track of the nodes of a cluster and which of them serves each slot. Slots are learned
+/// from `MOVED` redirects; commands for slots that have not been learned are sent to the first
+/// node.
+#[derive(Debug, Clone)]
+pub struct ClusterNodes {
+  /// The addresses of the known nodes.
+  nodes: Vec<String>,
+
+  /// The address serving each learned slot.
+  slots: std::collections::HashMap<u16, String>,
+}
+
+impl ClusterNodes {
+  /// Creates the slot map from the addresses of one or more cluster nodes.
+  pub fn new(nodes: Vec<String>) -> Self {
+    ClusterNodes {
+      nodes,
+      slots: std::collections::HashMap::new(),
+    }
+  }
+
+  /// Returns the address of the node that commands for the slot should be sent to.
+  pub fn node_for(&self, slot: u16) -> Option<&str> {
+    self.slots.get(&slot).or_else(|| self.nodes.first()).map(String::as_str)
+  }
+
+  /// Records the node serving a slot, as indicated by a redirect.
+  pub fn assign(&mut self, redirect: Redirect) {
+    if !self.nodes.contains(&redirect.addr) {
+      self.nodes.push(redirect.addr.clone());
+    }
+
+    self.slots.insert(redirect.slot, redirect.addr);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{command_key, crc16, key_slot, ClusterNodes, Redirect};
+
+  #[test]
+  fn test_key_slot() {
+    assert_eq!(key_slot("123456789"), 12739);
+    assert_eq!(key_slot("foo"), 12182);
+  }
+
+  #[test]
+  fn test_key_slot_hash_tag() {
+    assert_eq!(key_slot("{user1000}.following"), key_slot("user1000"));
+    assert_eq!(key_slot("{user1000}.followers"), key_slot("user1000"));
+    assert_eq!(key_slot("foo{}{bar}"), crc16(b"foo{}{bar}") % 16384);
+  }
+
+  #[test]
+  fn test_redirect_parse() {
+    assert_eq!(
+      Redirect::parse("-MOVED 3999 127.0.0.1:6381\r\n"),
+      Some(Redirect {
+        slot: 3999,
+        addr: String::from("127.0.0.1:6381"),
+      })
+    );
+    assert_eq!(Redirect::parse("-ASK 3999 127.0.0.1:6381"), None);
+    assert_eq!(Redirect::parse("-ERR unknown command"), None);
+  }
+
+  #[test]
+  fn test_command_key() {
+    let get = crate::StringCommand::Get::<_, &str>(crate::Arity::One("seinfeld"));
+    assert_eq!(command_key(&format!("{}", get)), Some(String::from("seinfeld")));
+    let echo = crate::Command::Echo::<_, &str>("hello");
+    assert_eq!(command_key(&format!("{}", echo)), None);
+    let group = crate::StreamCommand::CreateGroup::<_, &str>("episodes", "watchers", crate::StreamId::Min);
+    assert_eq!(command_key(&format!("{}", group)), Some(String::from("episodes")));
+  }
+
+  #[test]
+  fn test_command_key_xreadgroup() {
+    let read = crate::StreamCommand::ReadGroup::<_, &str> {
+      key: "episodes",
+      group: "STREAMS",
+      consumer: "STREAMS",
+      count: Some(1),
+    };
+    assert_eq!(command_key(&format!("{}", read)), Some(String::from("episodes")));
+  }
+
+  #[test]
+  fn test_cluster_nodes_assign() {
+    let mut nodes = ClusterNodes::new(vec![String::from("127.0.0.1:7000")]);
+    assert_eq!(nodes.node_for(3999), Some("127.0.0.1:7000"));
+    nodes.assign(Redirect {
+      slot: 3999,
+      addr: String::from("127.0.0.1:7001"),
+    });
+    assert_eq!(nodes.node_for(3999), Some("127.0.0.1:7001"));
+    assert_eq!(nodes.node_for(4000), Some("127.0.0.1:7000"));
+  }
+}
@@ -0,0 +1,94 @@
+use std::io::Error;
+use std::time::Duration;
+
+/// Opens tcp connections to redis with socket options applied before any command is sent. The
+/// `connect` method returns a `std::net::TcpStream`, or an `async_std::net::TcpStream` when the
+/// `kramer-async` feature is enabled.
+///
+/// ```no_run
+/// # #[cfg(not(feature = "kramer-async"))]
+/// # fn main() -> Result<(), std::io::Error> {
+/// use kramer::{execute, Command, ConnectionBuilder};
+/// use std::time::Duration;
+///
+/// let mut stream = ConnectionBuilder::new("127.0.0.1:6379")
+///   .nodelay(true)
+///   .keepalive(Some(Duration::from_secs(60)))
+///   .connect()?;
+/// execute(&mut stream, Command::Echo::<_, &str>("hello"))?;
+/// # Ok(())
+/// # }
+/// # #[cfg(feature = "kramer-async")]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectionBuilder {
+  /// The address of the redis server.
+  pub(crate) addr: String,
+
+  /// Whether Nagle's algorithm is disabled on the socket.
+  pub(crate) nodelay: bool,
+
+  /// The amount of idle time before keepalive probes are sent; `None` leaves keepalive off.
+  pub(crate) keepalive: Option<Duration>,
+}
+
+impl ConnectionBuilder {
+  /// Creates a builder for the address. Without further configuration, connections are opened
+  /// with the same options as `TcpStream::connect`.
+  pub fn new<A>(addr: A) -> Self
+  where
+    A: Into<String>,
+  {
+    ConnectionBuilder {
+      addr: addr.into(),
+      nodelay: false,
+      keepalive: None,
+    }
+  }
+
+  /// Sets `TCP_NODELAY`, sending writes immediately instead of coalescing small ones.
+  pub fn nodelay(mut self, nodelay: bool) -> Self {
+    self.nodelay = nodelay;
+    self
+  }
+
+  /// Enables `SO_KEEPALIVE`, probing the connection once it has been idle for the duration.
+  pub fn keepalive(mut self, idle: Option<Duration>) -> Self {
+    self.keepalive = idle;
+    self
+  }
+
+  /// Applies the configured options to a connected socket.
+  pub(crate) fn configure(&self, socket: socket2::SockRef) -> Result<(), Error> {
+    socket.set_nodelay(self.nodelay)?;
+
+    match self.keepalive {
+      Some(idle) => socket.set_tcp_keepalive(&socket2::TcpKeepalive::new().with_time(idle)),
+      None => socket.set_keepalive(false),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ConnectionBuilder;
+  use std::time::Duration;
+
+  #[test]
+  fn test_builder_defaults() {
+    let builder = ConnectionBuilder::new("127.0.0.1:6379");
+    assert_eq!(builder.addr, "127.0.0.1:6379");
+    assert!(!builder.nodelay);
+    assert_eq!(builder.keepalive, None);
+  }
+
+  #[test]
+  fn test_builder_options() {
+    let builder = ConnectionBuilder::new("127.0.0.1:6379")
+      .nodelay(true)
+      .keepalive(Some(Duration::from_secs(30)));
+    assert!(builder.nodelay);
+    assert_eq!(builder.keepalive, Some(Duration::from_secs(30)));
+  }
+}
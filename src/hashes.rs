@@ -7,7 +7,8 @@ pub enum HashCommand<S, V> {
   /// Deletes fields from a given hash.
   Del(S, Arity<S>),
 
-  /// Sets the value of a hash for a given key.
+  /// Sets the value of a hash for a given key. Many fields are written in the order they were
+  /// provided in.
   Set(S, Arity<(S, V)>, Insertion),
 
   /// Returns the value (or many) stored in a hash at a specific field.
@@ -22,6 +23,9 @@ pub enum HashCommand<S, V> {
   /// Increments a key for the hash by a given amount.
   Incr(S, S, i64),
 
+  /// Increments a key for the hash by a given floating point amount.
+  IncrFloat(S, S, f64),
+
   /// Returns all keys for the hash stored at a given key.
   Keys(S),
 
@@ -52,6 +56,15 @@ where
         );
         write!(formatter, "*4\r\n$7\r\nHINCRBY\r\n{}", tail)
       }
+      HashCommand::IncrFloat(key, field, amt) => {
+        let tail = format!(
+          "{}{}{}",
+          format_bulk_string(key),
+          format_bulk_string(field),
+          format_bulk_string(amt)
+        );
+        write!(formatter, "*4\r\n$12\r\nHINCRBYFLOAT\r\n{}", tail)
+      }
       HashCommand::Vals(key) => write!(formatter, "*2\r\n$5\r\nHVALS\r\n{}", format_bulk_string(key)),
       HashCommand::Keys(key) => write!(formatter, "*2\r\n$5\r\nHKEYS\r\n{}", format_bulk_string(key)),
       HashCommand::Len(key) => write!(formatter, "*2\r\n$4\r\nHLEN\r\n{}", format_bulk_string(key)),
@@ -33,25 +33,35 @@
 
 /// The response module contains parsing logic for redis responses.
 mod response;
-pub use response::{Response, ResponseLine, ResponseValue};
+pub use response::{Message, Response, ResponseLine, ResponseValue};
+
+/// Socket options applied when opening connections.
+mod connection;
+pub use connection::ConnectionBuilder;
 
 /// Our async_io module uses async-std.
 #[cfg(feature = "kramer-async")]
 mod async_io;
 #[cfg(feature = "kramer-async")]
-pub use async_io::{execute, read, send};
+pub use async_io::{
+  drain_list, execute, execute_cluster, get_and_extend, hincr_float, hset_all, incr_float, read, read_tagged, send,
+  subscribe_with,
+};
 
 /// Our sync_io module uses methods directly from ruststd.
 #[cfg(not(feature = "kramer-async"))]
 mod sync_io;
 #[cfg(not(feature = "kramer-async"))]
-pub use sync_io::{execute, read, send};
+pub use sync_io::{
+  drain_list, execute, execute_cluster, get_and_extend, hincr_float, hset_all, incr_float, read, read_tagged, send,
+  subscribe_with,
+};
 
 /// To consolidate the variants of any given command, this module exposes generic and common
 /// enumerations that extend the reason of any given enum.
 mod modifiers;
 use modifiers::format_bulk_string;
-pub use modifiers::{humanize_command, Arity, Insertion, Side};
+pub use modifiers::{humanize_command, Arity, ExpireCondition, Insertion, Side};
 
 /// List related enums.
 mod lists;
@@ -75,6 +85,14 @@ pub use strings::StringCommand;
 mod hashes;
 pub use hashes::HashCommand;
 
+/// Cluster slot computation and redirect handling.
+mod cluster;
+pub use cluster::{command_key, key_slot, ClusterNodes, Redirect, MAX_REDIRECTS, SLOT_COUNT};
+
+/// Stream related enums.
+mod streams;
+pub use streams::{StreamCommand, StreamId};
+
 /// Redis authorization supports password and user/password authorization schemes.
 #[derive(Debug)]
 pub enum AuthCredentials<S> {
@@ -115,6 +133,23 @@ pub enum Command<S, V> {
   /// Commands for checking the presence of keys.
   Exists(Arity<S>),
 
+  /// Sets the time to live of a key, optionally only under a condition. Durations of whole seconds
+  /// are sent as `EXPIRE`, otherwise `PEXPIRE` is used.
+  Expire(S, std::time::Duration, Option<ExpireCondition>),
+
+  /// Copies the value of a key to another key, replacing any existing value at the destination
+  /// when `true`.
+  Copy(S, S, bool),
+
+  /// Marks the start of a transaction.
+  Multi,
+
+  /// Executes every command queued since `Multi`.
+  Exec,
+
+  /// Returns details about the given commands; unknown commands are returned as nil.
+  CommandInfo(Arity<S>),
+
   /// Commands for working with list keys.
   Lists(ListCommand<S, V>),
 
@@ -127,12 +162,21 @@ pub enum Command<S, V> {
   /// Commands for working with set keys.
   Sets(SetCommand<S, V>),
 
+  /// Commands for working with stream keys.
+  Streams(StreamCommand<S, V>),
+
   /// The echo command will return the contents of the string sent.
   Echo(S),
 
   /// Auth commands
   Auth(AuthCredentials<S>),
 
+  /// Subscribes the connection to one or more channels.
+  Subscribe(Arity<S>),
+
+  /// Posts a message to a channel, returning the amount of clients that received it.
+  Publish(S, V),
+
   /// ACL commands; currently unstable.
   #[cfg(feature = "acl")]
   Acl(AclCommand<S>),
@@ -151,12 +195,72 @@ where
       Command::Auth(method) => write!(formatter, "{}", method),
       Command::Echo(value) => write!(formatter, "*2\r\n$4\r\nECHO\r\n{}", format_bulk_string(value)),
       Command::Keys(value) => write!(formatter, "*2\r\n$4\r\nKEYS\r\n{}", format_bulk_string(value)),
+      Command::Subscribe(Arity::One(channel)) => {
+        write!(formatter, "*2\r\n$9\r\nSUBSCRIBE\r\n{}", format_bulk_string(channel))
+      }
+      Command::Subscribe(Arity::Many(channels)) => {
+        let len = channels.len();
+        let right = channels.iter().map(format_bulk_string).collect::<String>();
+        write!(formatter, "*{}\r\n$9\r\nSUBSCRIBE\r\n{}", len + 1, right)
+      }
+      Command::Publish(channel, message) => write!(
+        formatter,
+        "*3\r\n$7\r\nPUBLISH\r\n{}{}",
+        format_bulk_string(channel),
+        format_bulk_string(message)
+      ),
       Command::Exists(Arity::Many(values)) => {
         let len = values.len();
         let right = values.iter().map(format_bulk_string).collect::<String>();
         write!(formatter, "*{}\r\n$6\r\nEXISTS\r\n{}", len + 1, right)
       }
       Command::Exists(Arity::One(value)) => write!(formatter, "*2\r\n$6\r\nEXISTS\r\n{}", format_bulk_string(value)),
+      Command::Expire(key, ttl, condition) => {
+        let (cmd, amount) = match ttl.as_millis() {
+          millis if millis % 1000 == 0 => ("EXPIRE", u128::from(ttl.as_secs())),
+          millis => ("PEXPIRE", millis),
+        };
+        let (cc, condition) = match condition {
+          Some(condition) => (1, format_bulk_string(condition)),
+          None => (0, "".to_string()),
+        };
+        write!(
+          formatter,
+          "*{}\r\n{}{}{}{}",
+          3 + cc,
+          format_bulk_string(cmd),
+          format_bulk_string(key),
+          format_bulk_string(amount),
+          condition
+        )
+      }
+      Command::Copy(source, destination, replace) => {
+        let (rc, replace) = if *replace {
+          (1, format_bulk_string("REPLACE"))
+        } else {
+          (0, "".to_string())
+        };
+        write!(
+          formatter,
+          "*{}\r\n$4\r\nCOPY\r\n{}{}{}",
+          3 + rc,
+          format_bulk_string(source),
+          format_bulk_string(destination),
+          replace
+        )
+      }
+      Command::Multi => write!(formatter, "*1\r\n$5\r\nMULTI\r\n"),
+      Command::Exec => write!(formatter, "*1\r\n$4\r\nEXEC\r\n"),
+      Command::CommandInfo(Arity::One(name)) => write!(
+        formatter,
+        "*3\r\n$7\r\nCOMMAND\r\n$4\r\nINFO\r\n{}",
+        format_bulk_string(name)
+      ),
+      Command::CommandInfo(Arity::Many(names)) => {
+        let len = names.len();
+        let right = names.iter().map(format_bulk_string).collect::<String>();
+        write!(formatter, "*{}\r\n$7\r\nCOMMAND\r\n$4\r\nINFO\r\n{}", len + 2, right)
+      }
       Command::Del(Arity::One(value)) => write!(formatter, "*2\r\n$3\r\nDEL\r\n{}", format_bulk_string(value)),
       Command::Del(Arity::Many(values)) => {
         let len = values.len();
@@ -167,6 +271,7 @@ where
       Command::Strings(string_command) => write!(formatter, "{}", string_command),
       Command::Hashes(hash_command) => write!(formatter, "{}", hash_command),
       Command::Sets(set_command) => write!(formatter, "{}", set_command),
+      Command::Streams(stream_command) => write!(formatter, "{}", stream_command),
     }
   }
 }
@@ -214,13 +319,10 @@ where
       let header = header(content_size);
       for b in header.as_bytes() {
         if count >= outbound.len() {
-          let err = std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!(
-              "Unable to inject command 'header' into buffer at position {count} (of {})",
-              outbound.len()
-            ),
-          );
+          let err = std::io::Error::other(format!(
+            "Unable to inject command 'header' into buffer at position {count} (of {})",
+            outbound.len()
+          ));
           return std::task::Poll::Ready(Err(err));
         }
 
@@ -285,7 +387,9 @@ where
 
 #[cfg(test)]
 mod fmt_tests {
-  use super::{Arity, AuthCredentials, Command, HashCommand, Insertion, ListCommand, Side, StringCommand};
+  use super::{
+    Arity, AuthCredentials, Command, ExpireCondition, HashCommand, Insertion, ListCommand, Side, StringCommand,
+  };
   use std::io::Write;
 
   #[test]
@@ -684,6 +788,88 @@ mod fmt_tests {
     assert_eq!(format!("{}", cmd), String::from("*2\r\n$4\r\nECHO\r\n$5\r\nhello\r\n"));
   }
 
+  #[test]
+  fn test_expire_seconds() {
+    let cmd = Command::Expire::<_, &str>("seinfeld", std::time::Duration::from_secs(10), None);
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$6\r\nEXPIRE\r\n$8\r\nseinfeld\r\n$2\r\n10\r\n")
+    );
+  }
+
+  #[test]
+  fn test_expire_millis() {
+    let cmd = Command::Expire::<_, &str>("seinfeld", std::time::Duration::from_millis(1500), None);
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$7\r\nPEXPIRE\r\n$8\r\nseinfeld\r\n$4\r\n1500\r\n")
+    );
+  }
+
+  #[test]
+  fn test_expire_condition() {
+    let cmd = Command::Expire::<_, &str>(
+      "seinfeld",
+      std::time::Duration::from_millis(1500),
+      Some(ExpireCondition::NoExpiry),
+    );
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*4\r\n$7\r\nPEXPIRE\r\n$8\r\nseinfeld\r\n$4\r\n1500\r\n$2\r\nNX\r\n")
+    );
+  }
+
+  #[test]
+  fn test_copy() {
+    let cmd = Command::Copy::<_, &str>("seinfeld", "newman", false);
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$4\r\nCOPY\r\n$8\r\nseinfeld\r\n$6\r\nnewman\r\n")
+    );
+  }
+
+  #[test]
+  fn test_multi_exec() {
+    assert_eq!(format!("{}", Command::Multi::<&str, &str>), "*1\r\n$5\r\nMULTI\r\n");
+    assert_eq!(format!("{}", Command::Exec::<&str, &str>), "*1\r\n$4\r\nEXEC\r\n");
+  }
+
+  #[test]
+  fn test_command_info() {
+    let cmd = Command::CommandInfo::<_, &str>(Arity::One("getex"));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$7\r\nCOMMAND\r\n$4\r\nINFO\r\n$5\r\ngetex\r\n")
+    );
+  }
+
+  #[test]
+  fn test_subscribe() {
+    let cmd = Command::Subscribe::<&str, &str>(Arity::One("episodes"));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*2\r\n$9\r\nSUBSCRIBE\r\n$8\r\nepisodes\r\n")
+    );
+  }
+
+  #[test]
+  fn test_subscribe_many() {
+    let cmd = Command::Subscribe::<&str, &str>(Arity::Many(vec!["episodes", "seasons"]));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$9\r\nSUBSCRIBE\r\n$8\r\nepisodes\r\n$7\r\nseasons\r\n")
+    );
+  }
+
+  #[test]
+  fn test_publish() {
+    let cmd = Command::Publish("episodes", "pilot");
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$7\r\nPUBLISH\r\n$8\r\nepisodes\r\n$5\r\npilot\r\n")
+    );
+  }
+
   #[test]
   fn test_hset_many() {
     let cmd = Command::Hashes::<&str, &str>(HashCommand::Set(
@@ -699,6 +885,24 @@ mod fmt_tests {
     );
   }
 
+  #[test]
+  fn test_hset_many_preserves_order() {
+    let cmd = Command::Hashes::<&str, &str>(HashCommand::Set(
+      "seinfeld",
+      Arity::Many(vec![("name", "kramer"), ("apartment", "5b"), ("friend", "jerry")]),
+      Insertion::Always,
+    ));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from(concat!(
+        "*8\r\n$4\r\nHSET\r\n$8\r\nseinfeld\r\n",
+        "$4\r\nname\r\n$6\r\nkramer\r\n",
+        "$9\r\napartment\r\n$2\r\n5b\r\n",
+        "$6\r\nfriend\r\n$5\r\njerry\r\n"
+      ))
+    );
+  }
+
   #[test]
   fn test_hgetall() {
     let cmd = Command::Hashes::<&str, &str>(HashCommand::Get("seinfeld", None));
@@ -743,6 +947,15 @@ mod fmt_tests {
     );
   }
 
+  #[test]
+  fn test_hincrbyfloat() {
+    let cmd = Command::Hashes::<&str, &str>(HashCommand::IncrFloat("kramer", "episodes", 0.5));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*4\r\n$12\r\nHINCRBYFLOAT\r\n$6\r\nkramer\r\n$8\r\nepisodes\r\n$3\r\n0.5\r\n")
+    );
+  }
+
   #[test]
   fn test_hlen() {
     let cmd = Command::Hashes::<&str, &str>(HashCommand::Len("seinfeld"));
@@ -824,6 +1037,46 @@ mod fmt_tests {
     );
   }
 
+  #[test]
+  fn test_rpop_many_fmt() {
+    let cmd = Command::Lists::<_, &str>(ListCommand::PopMany(Side::Right, "seinfeld", 10));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$4\r\nRPOP\r\n$8\r\nseinfeld\r\n$2\r\n10\r\n")
+    );
+  }
+
+  #[test]
+  fn test_lpos() {
+    let cmd = Command::Lists::<_, &str>(ListCommand::Pos("episodes", "pilot", None, None));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$4\r\nLPOS\r\n$8\r\nepisodes\r\n$5\r\npilot\r\n")
+    );
+  }
+
+  #[test]
+  fn test_lpos_rank_count() {
+    let cmd = Command::Lists::<_, &str>(ListCommand::Pos("episodes", "pilot", Some(2), Some(3)));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from(
+        "*7\r\n$4\r\nLPOS\r\n$8\r\nepisodes\r\n$5\r\npilot\r\n$4\r\nRANK\r\n$1\r\n2\r\n$5\r\nCOUNT\r\n$1\r\n3\r\n"
+      )
+    );
+  }
+
+  #[test]
+  fn test_lpos_negative_rank_count() {
+    let cmd = Command::Lists::<_, &str>(ListCommand::Pos("episodes", "pilot", Some(-1), Some(0)));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from(
+        "*7\r\n$4\r\nLPOS\r\n$8\r\nepisodes\r\n$5\r\npilot\r\n$4\r\nRANK\r\n$2\r\n-1\r\n$5\r\nCOUNT\r\n$1\r\n0\r\n"
+      )
+    );
+  }
+
   #[test]
   fn test_lset() {
     let cmd = Command::Lists::<_, &str>(ListCommand::Set("episodes", 1, "pilot"));
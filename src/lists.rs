@@ -6,18 +6,40 @@ pub enum ListCommand<S, V> {
   /// List length.
   Len(S),
 
-  /// Adds an item to the list on the correct side.
+  /// Adds an item to the list on the correct side. Many values are applied one at a time from
+  /// left to right, which means pushing `a`, `b`, `c` onto the left of a list will result in
+  /// `c`, `b`, `a`, while pushing them onto the right will result in `a`, `b`, `c`.
+  ///
+  /// ```
+  /// use kramer::{humanize_command, Arity, Command, Insertion, ListCommand, Side};
+  ///
+  /// let push = Command::Lists::<_, &str>(ListCommand::Push(
+  ///   (Side::Left, Insertion::Always),
+  ///   "seinfeld",
+  ///   Arity::Many(vec!["a", "b", "c"]),
+  /// ));
+  ///
+  /// // After this, `LRANGE seinfeld 0 -1` returns `c`, `b`, `a`.
+  /// assert_eq!(humanize_command(&push), "LPUSH seinfeld a b c");
+  /// ```
   Push((Side, Insertion), S, Arity<V>),
 
   ///  Pops an item from the side of a list with the option for a timeout.
   Pop(Side, S, Option<(Option<Arity<S>>, u64)>),
 
+  /// Pops up to the given amount of items from the side of a list; requires redis 6.2 or later.
+  PopMany(Side, S, usize),
+
   /// Removes items from a list.
   Rem(S, V, u64),
 
   /// Returns the index of an item in a list.
   Index(S, i64),
 
+  /// Returns the position of the elements matching a value, with an optional rank (the match to
+  /// start from; negative ranks search from the tail) and count (the amount of positions to return).
+  Pos(S, V, Option<i64>, Option<usize>),
+
   /// Sets the value of an index of a list.
   Set(S, u64, V),
 
@@ -75,6 +97,28 @@ where
         let tail = format!("{}{}", format_bulk_string(key), format_bulk_string(amt));
         write!(formatter, "*3\r\n$6\r\nLINDEX\r\n{}", tail)
       }
+      ListCommand::Pos(key, element, rank, count) => {
+        let (rc, rank) = match rank {
+          Some(rank) => (2, format!("{}{}", format_bulk_string("RANK"), format_bulk_string(rank))),
+          None => (0, "".to_string()),
+        };
+        let (cc, count) = match count {
+          Some(count) => (
+            2,
+            format!("{}{}", format_bulk_string("COUNT"), format_bulk_string(count)),
+          ),
+          None => (0, "".to_string()),
+        };
+        write!(
+          formatter,
+          "*{}\r\n$4\r\nLPOS\r\n{}{}{}{}",
+          3 + rc + cc,
+          format_bulk_string(key),
+          format_bulk_string(element),
+          rank,
+          count
+        )
+      }
       ListCommand::Rem(key, value, count) => {
         let end = format!(
           "{}{}{}",
@@ -121,6 +165,19 @@ where
           ext
         )
       }
+      ListCommand::PopMany(side, key, count) => {
+        let cmd = match side {
+          Side::Left => "LPOP",
+          Side::Right => "RPOP",
+        };
+        write!(
+          formatter,
+          "*3\r\n$4\r\n{}\r\n{}{}",
+          cmd,
+          format_bulk_string(key),
+          format_bulk_string(count)
+        )
+      }
       ListCommand::Push(operation, k, Arity::One(v)) => {
         let cmd = match operation {
           (Side::Left, Insertion::IfExists) => "LPUSHX",
@@ -23,6 +23,34 @@ pub enum Insertion {
   IfNotExists,
 }
 
+/// Redis 7 allows an expiration to be applied conditionally, based on the current time to live of
+/// the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpireCondition {
+  /// Only applied when the key has no time to live (`NX`).
+  NoExpiry,
+
+  /// Only applied when the key already has a time to live (`XX`).
+  HasExpiry,
+
+  /// Only applied when the new time to live is greater than the current one (`GT`).
+  Greater,
+
+  /// Only applied when the new time to live is less than the current one (`LT`).
+  Less,
+}
+
+impl std::fmt::Display for ExpireCondition {
+  fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      ExpireCondition::NoExpiry => write!(formatter, "NX"),
+      ExpireCondition::HasExpiry => write!(formatter, "XX"),
+      ExpireCondition::Greater => write!(formatter, "GT"),
+      ExpireCondition::Less => write!(formatter, "LT"),
+    }
+  }
+}
+
 /// The arity type here is used to mean a single or non-single container.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Arity<S> {
@@ -73,4 +101,26 @@ mod tests {
     let humanized = humanize_command(&command);
     assert_eq!(humanized, "AUTH testing testerton");
   }
+
+  #[test]
+  fn test_humanize_copy_replace() {
+    let command = crate::Command::Copy::<&str, &str>("seinfeld", "newman", true);
+    assert_eq!(humanize_command(&command), "COPY seinfeld newman REPLACE");
+  }
+
+  #[test]
+  fn test_humanize_getex_persist() {
+    let command = crate::Command::Strings::<&str, &str>(crate::StringCommand::GetEx("seinfeld", None));
+    assert_eq!(humanize_command(&command), "GETEX seinfeld PERSIST");
+  }
+
+  #[test]
+  fn test_humanize_expire_gt() {
+    let command = crate::Command::Expire::<&str, &str>(
+      "seinfeld",
+      std::time::Duration::from_secs(10),
+      Some(super::ExpireCondition::Greater),
+    );
+    assert_eq!(humanize_command(&command), "EXPIRE seinfeld 10 GT");
+  }
 }
@@ -23,8 +23,25 @@ pub enum ResponseLine {
   Null,
 }
 
-/// A redis response value may either be empty, a bulk string, or an integer.
+impl ResponseLine {
+  /// Returns the name of the RESP type this line was parsed as. Unlike the tags of `Response` and
+  /// `ResponseValue`, simple strings and bulk strings are reported separately here.
+  pub fn type_tag(&self) -> &'static str {
+    match self {
+      ResponseLine::Array(_) => "array",
+      ResponseLine::SimpleString(_) => "simple-string",
+      ResponseLine::Error(_) => "error",
+      ResponseLine::Integer(_) => "integer",
+      ResponseLine::BulkString(_) => "bulk-string",
+      ResponseLine::Null => "nil",
+    }
+  }
+}
+
+/// A redis response value may either be empty, a bulk string, an integer, or an array nested
+/// within an array response.
 #[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ResponseValue {
   /// The empty response.
   Empty,
@@ -34,6 +51,21 @@ pub enum ResponseValue {
 
   /// Integer responses.
   Integer(i64),
+
+  /// Arrays nested within array responses.
+  Array(Vec<ResponseValue>),
+}
+
+impl ResponseValue {
+  /// Returns the name of the type of this value, useful for diagnostics.
+  pub fn type_tag(&self) -> &'static str {
+    match self {
+      ResponseValue::Empty => "nil",
+      ResponseValue::String(_) => "string",
+      ResponseValue::Integer(_) => "integer",
+      ResponseValue::Array(_) => "array",
+    }
+  }
 }
 
 /// Redis responses may either be an array of values, a single value, or an error.
@@ -49,6 +81,152 @@ pub enum Response {
   Error,
 }
 
+impl Response {
+  /// Returns the name of the type of this response, useful for diagnostics. Single values report
+  /// the tag of the value they hold.
+  pub fn type_tag(&self) -> &'static str {
+    match self {
+      Response::Array(_) => "array",
+      Response::Item(value) => value.type_tag(),
+      Response::Error => "error",
+    }
+  }
+}
+
+/// A message delivered to a connection that has subscribed to the channel it was published on.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Message {
+  /// The channel the message was published to.
+  pub channel: String,
+
+  /// The content of the message.
+  pub payload: String,
+}
+
+/// Subscribed connections receive every message as a three element array; the first element
+/// distinguishes published messages from subscription confirmations, which are ignored here.
+pub fn published_message(response: Response) -> Option<Message> {
+  match response {
+    Response::Array(mut values) if values.len() == 3 => {
+      let (payload, channel) = (values.pop(), values.pop());
+      match (values.pop(), channel, payload) {
+        (
+          Some(ResponseValue::String(kind)),
+          Some(ResponseValue::String(channel)),
+          Some(ResponseValue::String(payload)),
+        ) if kind == "message" => Some(Message { channel, payload }),
+        _ => None,
+      }
+    }
+    _ => None,
+  }
+}
+
+/// Arrays may contain other arrays. As the lines of an array response are read, this keeps track
+/// of every array that has been opened but not yet filled.
+pub struct ArrayAccumulator {
+  /// The expected size and received elements of each open array, outermost first.
+  frames: Vec<(usize, Vec<ResponseValue>)>,
+}
+
+impl ArrayAccumulator {
+  /// Starts accumulating the elements of an array with the given amount of elements.
+  pub fn new(size: usize) -> Self {
+    ArrayAccumulator {
+      frames: vec![(size, Vec::with_capacity(size))],
+    }
+  }
+
+  /// Adds a value to the innermost open array. Once the outermost array has received all of its
+  /// elements, they are returned.
+  pub fn push(&mut self, mut value: ResponseValue) -> Option<Vec<ResponseValue>> {
+    while let Some((size, mut values)) = self.frames.pop() {
+      values.push(value);
+
+      if values.len() < size {
+        self.frames.push((size, values));
+        return None;
+      }
+
+      if self.frames.is_empty() {
+        return Some(values);
+      }
+
+      value = ResponseValue::Array(values);
+    }
+
+    None
+  }
+
+  /// Opens a nested array with the given amount of elements.
+  pub fn open(&mut self, size: usize) -> Option<Vec<ResponseValue>> {
+    if size == 0 {
+      return self.push(ResponseValue::Array(vec![]));
+    }
+
+    self.frames.push((size, Vec::with_capacity(size)));
+    None
+  }
+
+  /// The amount of elements the outermost array has received.
+  pub fn received(&self) -> usize {
+    self.frames.first().map(|(_, values)| values.len()).unwrap_or_default()
+  }
+}
+
+/// `COMMAND INFO` replies with an array holding the details of each requested command, or nil for
+/// commands the server does not know about.
+pub fn command_present(info: &Response) -> bool {
+  matches!(info, Response::Array(details) if matches!(details.first(), Some(ResponseValue::Array(_))))
+}
+
+/// Returns the arity of the first command described by a `COMMAND INFO` reply. Negative arities
+/// indicate commands that accept a variable amount of arguments.
+pub fn command_arity(info: &Response) -> Option<i64> {
+  match info {
+    Response::Array(details) => match details.first() {
+      Some(ResponseValue::Array(command)) => match command.get(1) {
+        Some(ResponseValue::Integer(arity)) => Some(*arity),
+        _ => None,
+      },
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+/// A transaction that reads a value and then modifies the key returns the replies of both from
+/// `EXEC`; only the first is of interest.
+pub fn transaction_value(replies: Response) -> Result<Response, Error> {
+  match replies {
+    Response::Array(values) if values.len() == 2 => match values.into_iter().next() {
+      Some(value) => Ok(Response::Item(value)),
+      None => Err(Error::new(ErrorKind::InvalidData, "empty transaction reply")),
+    },
+    other => Err(Error::new(
+      ErrorKind::InvalidData,
+      format!("unexpected transaction reply {:?}", other),
+    )),
+  }
+}
+
+/// Float values are returned from redis as strings; this will attempt to parse a single string
+/// response into an `f64`.
+pub fn float_value(response: Response) -> Result<f64, Error> {
+  match response {
+    Response::Item(ResponseValue::String(value)) => value.parse::<f64>().map_err(|e| {
+      Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid float value '{}': {}", value, e),
+      )
+    }),
+    other => Err(Error::new(
+      ErrorKind::InvalidData,
+      format!("expected a float value and received {:?}", other),
+    )),
+  }
+}
+
 /// Most redis responses will be a bulk string, or an integer. In either case, we want to parse
 /// this as a usize and return that value. We're also translating from an integer `-1` value into a
 /// `None` to represent an empty value.
@@ -57,12 +235,7 @@ fn read_line_size(line: String) -> Result<Option<usize>, Error> {
     "-1" => Ok(None),
     value => value
       .parse::<usize>()
-      .map_err(|e| {
-        Error::new(
-          ErrorKind::Other,
-          format!("invalid array length value '{}': {}", line.as_str(), e),
-        )
-      })
+      .map_err(|e| Error::other(format!("invalid array length value '{}': {}", line.as_str(), e)))
       .map(Some),
   }
 }
@@ -84,16 +257,99 @@ pub fn readline(result: String) -> Result<ResponseLine, Error> {
       let (_, rest) = result.trim_end().split_at(1);
       rest
         .parse::<i64>()
-        .map_err(|e| Error::new(ErrorKind::Other, format!("{:?}", e)))
+        .map_err(|e| Error::other(format!("{:?}", e)))
         .map(ResponseLine::Integer)
     }
-    Some(unknown) => Err(Error::new(
-      ErrorKind::Other,
-      format!("invalid message byte leader: {}", unknown),
-    )),
-    None => Err(Error::new(
-      ErrorKind::Other,
-      "empty line in response, unable to determine type",
-    )),
+    Some(unknown) => Err(Error::other(format!("invalid message byte leader: {}", unknown))),
+    None => Err(Error::other("empty line in response, unable to determine type")),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{command_arity, float_value, readline, ArrayAccumulator, Response, ResponseValue};
+
+  #[test]
+  fn test_array_accumulator_nested() {
+    let mut accumulator = ArrayAccumulator::new(3);
+    assert_eq!(accumulator.push(ResponseValue::String("kramer".into())), None);
+    assert_eq!(accumulator.open(2), None);
+    assert_eq!(accumulator.push(ResponseValue::Integer(1)), None);
+    assert_eq!(accumulator.push(ResponseValue::Empty), None);
+    assert_eq!(accumulator.received(), 2);
+    assert_eq!(
+      accumulator.open(0),
+      Some(vec![
+        ResponseValue::String("kramer".into()),
+        ResponseValue::Array(vec![ResponseValue::Integer(1), ResponseValue::Empty]),
+        ResponseValue::Array(vec![]),
+      ])
+    );
+  }
+
+  #[test]
+  fn test_command_arity() {
+    let info = |arity| {
+      Response::Array(vec![ResponseValue::Array(vec![
+        ResponseValue::String("rpop".into()),
+        ResponseValue::Integer(arity),
+      ])])
+    };
+    assert_eq!(command_arity(&info(-2)), Some(-2));
+    assert_eq!(command_arity(&info(2)), Some(2));
+    assert_eq!(command_arity(&Response::Array(vec![ResponseValue::Empty])), None);
+  }
+
+  #[test]
+  fn test_float_value() {
+    let value = float_value(Response::Item(ResponseValue::String("5.6".into()))).expect("parsed");
+    assert_eq!(value, 5.6);
+  }
+
+  #[test]
+  fn test_float_value_invalid() {
+    assert!(float_value(Response::Item(ResponseValue::String("kramer".into()))).is_err());
+    assert!(float_value(Response::Item(ResponseValue::Empty)).is_err());
+  }
+
+  #[test]
+  fn test_response_type_tags() {
+    assert_eq!(Response::Array(vec![]).type_tag(), "array");
+    assert_eq!(Response::Item(ResponseValue::Empty).type_tag(), "nil");
+    assert_eq!(
+      Response::Item(ResponseValue::String("kramer".into())).type_tag(),
+      "string"
+    );
+    assert_eq!(Response::Item(ResponseValue::Integer(1)).type_tag(), "integer");
+    assert_eq!(Response::Error.type_tag(), "error");
+  }
+
+  #[test]
+  fn test_response_value_type_tags() {
+    assert_eq!(ResponseValue::Empty.type_tag(), "nil");
+    assert_eq!(ResponseValue::String("kramer".into()).type_tag(), "string");
+    assert_eq!(ResponseValue::Integer(-1).type_tag(), "integer");
+    assert_eq!(ResponseValue::Array(vec![]).type_tag(), "array");
+  }
+
+  #[test]
+  fn test_response_line_type_tags() {
+    let tags = ["*2", "+OK", "-ERR unknown", ":10", "$6", "$-1", "*-1"]
+      .iter()
+      .map(|line| readline(line.to_string()).map(|parsed| parsed.type_tag()))
+      .collect::<Result<Vec<&str>, std::io::Error>>()
+      .expect("parsed");
+    assert_eq!(
+      tags,
+      vec![
+        "array",
+        "simple-string",
+        "error",
+        "integer",
+        "bulk-string",
+        "nil",
+        "nil"
+      ]
+    );
   }
 }
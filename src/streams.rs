@@ -0,0 +1,340 @@
+use crate::modifiers::{format_bulk_string, Arity};
+
+/// Stream entries are identified by a millisecond timestamp and a sequence number. Ranges may also
+/// be bounded by the smallest and largest possible ids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamId {
+  /// The smallest possible id (`-`).
+  Min,
+
+  /// The largest possible id (`+`).
+  Max,
+
+  /// A specific id; the millisecond timestamp and sequence number.
+  Id(u64, u64),
+}
+
+impl std::fmt::Display for StreamId {
+  fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      StreamId::Min => write!(formatter, "-"),
+      StreamId::Max => write!(formatter, "+"),
+      StreamId::Id(millis, sequence) => write!(formatter, "{}-{}", millis, sequence),
+    }
+  }
+}
+
+/// Streams.
+#[derive(Debug)]
+pub enum StreamCommand<S, V> {
+  /// Appends an entry to a stream. Without an id, one will be generated by redis.
+  Add(S, Option<StreamId>, Arity<(S, V)>),
+
+  /// Creates a consumer group for a stream, creating the stream if necessary. Entries after the
+  /// provided id will be delivered to the group.
+  CreateGroup(S, S, StreamId),
+
+  /// Reads entries that have not yet been delivered to any consumer of the group.
+  ReadGroup {
+    /// The stream to read from.
+    key: S,
+
+    /// The consumer group reading.
+    group: S,
+
+    /// The consumer within the group that entries will be delivered to.
+    consumer: S,
+
+    /// The maximum amount of entries to read.
+    count: Option<usize>,
+  },
+
+  /// Transfers ownership of entries that have been pending for at least `min_idle` milliseconds
+  /// to the consumer. The reply contains the cursor to continue from and the claimed entries.
+  AutoClaim {
+    /// The stream the group belongs to.
+    key: S,
+
+    /// The consumer group that owns the pending entries.
+    group: S,
+
+    /// The consumer that will claim the entries.
+    consumer: S,
+
+    /// The minimum amount of milliseconds an entry must have been pending for.
+    min_idle: u64,
+
+    /// The id to start scanning pending entries from.
+    start: StreamId,
+
+    /// The maximum amount of entries to claim.
+    count: Option<usize>,
+  },
+
+  /// Inspects the entries that have been delivered to the group but not yet acknowledged. Without
+  /// a range, a summary of the pending entries is returned. With a range (start, end and count),
+  /// the individual entries are returned, optionally only those of a single consumer.
+  Pending {
+    /// The stream the group belongs to.
+    key: S,
+
+    /// The consumer group to inspect.
+    group: S,
+
+    /// The start id, end id and maximum amount of entries to return.
+    range: Option<(StreamId, StreamId, usize)>,
+
+    /// Limits the entries to those of a consumer; only sent alongside a range.
+    consumer: Option<S>,
+  },
+}
+
+impl<S, V> std::fmt::Display for StreamCommand<S, V>
+where
+  S: std::fmt::Display,
+  V: std::fmt::Display,
+{
+  fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      StreamCommand::Add(key, id, entries) => {
+        let id = match id {
+          Some(id) => format_bulk_string(id),
+          None => format_bulk_string("*"),
+        };
+        let (count, tail) = match entries {
+          Arity::One((field, value)) => (1, format!("{}{}", format_bulk_string(field), format_bulk_string(value))),
+          Arity::Many(entries) => (
+            entries.len(),
+            entries
+              .iter()
+              .map(|(field, value)| format!("{}{}", format_bulk_string(field), format_bulk_string(value)))
+              .collect::<String>(),
+          ),
+        };
+        write!(
+          formatter,
+          "*{}\r\n$4\r\nXADD\r\n{}{}{}",
+          3 + (count * 2),
+          format_bulk_string(key),
+          id,
+          tail
+        )
+      }
+      StreamCommand::CreateGroup(key, group, id) => write!(
+        formatter,
+        "*6\r\n$6\r\nXGROUP\r\n$6\r\nCREATE\r\n{}{}{}$8\r\nMKSTREAM\r\n",
+        format_bulk_string(key),
+        format_bulk_string(group),
+        format_bulk_string(id)
+      ),
+      StreamCommand::ReadGroup {
+        key,
+        group,
+        consumer,
+        count,
+      } => {
+        let (cc, count) = match count {
+          Some(count) => (
+            2,
+            format!("{}{}", format_bulk_string("COUNT"), format_bulk_string(count)),
+          ),
+          None => (0, "".to_string()),
+        };
+        write!(
+          formatter,
+          "*{}\r\n$10\r\nXREADGROUP\r\n$5\r\nGROUP\r\n{}{}{}$7\r\nSTREAMS\r\n{}$1\r\n>\r\n",
+          7 + cc,
+          format_bulk_string(group),
+          format_bulk_string(consumer),
+          count,
+          format_bulk_string(key)
+        )
+      }
+      StreamCommand::AutoClaim {
+        key,
+        group,
+        consumer,
+        min_idle,
+        start,
+        count,
+      } => {
+        let (cc, count) = match count {
+          Some(count) => (
+            2,
+            format!("{}{}", format_bulk_string("COUNT"), format_bulk_string(count)),
+          ),
+          None => (0, "".to_string()),
+        };
+        let tail = format!(
+          "{}{}{}{}{}",
+          format_bulk_string(key),
+          format_bulk_string(group),
+          format_bulk_string(consumer),
+          format_bulk_string(min_idle),
+          format_bulk_string(start)
+        );
+        write!(formatter, "*{}\r\n$10\r\nXAUTOCLAIM\r\n{}{}", 6 + cc, tail, count)
+      }
+      StreamCommand::Pending {
+        key,
+        group,
+        range: None,
+        ..
+      } => write!(
+        formatter,
+        "*3\r\n$8\r\nXPENDING\r\n{}{}",
+        format_bulk_string(key),
+        format_bulk_string(group)
+      ),
+      StreamCommand::Pending {
+        key,
+        group,
+        range: Some((start, end, count)),
+        consumer,
+      } => {
+        let (cc, consumer) = match consumer {
+          Some(consumer) => (1, format_bulk_string(consumer)),
+          None => (0, "".to_string()),
+        };
+        let tail = format!(
+          "{}{}{}{}{}{}",
+          format_bulk_string(key),
+          format_bulk_string(group),
+          format_bulk_string(start),
+          format_bulk_string(end),
+          format_bulk_string(count),
+          consumer
+        );
+        write!(formatter, "*{}\r\n$8\r\nXPENDING\r\n{}", 6 + cc, tail)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Arity, StreamCommand, StreamId};
+
+  #[test]
+  fn test_stream_id() {
+    assert_eq!(format!("{}", StreamId::Min), "-");
+    assert_eq!(format!("{}", StreamId::Max), "+");
+    assert_eq!(format!("{}", StreamId::Id(1526919030474, 55)), "1526919030474-55");
+  }
+
+  #[test]
+  fn test_xadd() {
+    let cmd = StreamCommand::Add("episodes", None, Arity::One(("name", "pilot")));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*5\r\n$4\r\nXADD\r\n$8\r\nepisodes\r\n$1\r\n*\r\n$4\r\nname\r\n$5\r\npilot\r\n")
+    );
+  }
+
+  #[test]
+  fn test_xgroup_create() {
+    let cmd = StreamCommand::CreateGroup::<_, &str>("episodes", "watchers", StreamId::Id(0, 0));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from(
+        "*6\r\n$6\r\nXGROUP\r\n$6\r\nCREATE\r\n$8\r\nepisodes\r\n$8\r\nwatchers\r\n$3\r\n0-0\r\n$8\r\nMKSTREAM\r\n"
+      )
+    );
+  }
+
+  #[test]
+  fn test_xreadgroup_count() {
+    let cmd = StreamCommand::ReadGroup::<_, &str> {
+      key: "episodes",
+      group: "watchers",
+      consumer: "jerry",
+      count: Some(1),
+    };
+    assert_eq!(
+      format!("{}", cmd),
+      String::from(
+        "*9\r\n$10\r\nXREADGROUP\r\n$5\r\nGROUP\r\n$8\r\nwatchers\r\n$5\r\njerry\r\n$5\r\nCOUNT\r\n$1\r\n1\r\n$7\r\nSTREAMS\r\n$8\r\nepisodes\r\n$1\r\n>\r\n"
+      )
+    );
+  }
+
+  #[test]
+  fn test_xautoclaim() {
+    let cmd = StreamCommand::AutoClaim::<_, &str> {
+      key: "episodes",
+      group: "watchers",
+      consumer: "elaine",
+      min_idle: 3600000,
+      start: StreamId::Id(0, 0),
+      count: None,
+    };
+    assert_eq!(
+      format!("{}", cmd),
+      String::from(
+        "*6\r\n$10\r\nXAUTOCLAIM\r\n$8\r\nepisodes\r\n$8\r\nwatchers\r\n$6\r\nelaine\r\n$7\r\n3600000\r\n$3\r\n0-0\r\n"
+      )
+    );
+  }
+
+  #[test]
+  fn test_xautoclaim_count() {
+    let cmd = StreamCommand::AutoClaim::<_, &str> {
+      key: "episodes",
+      group: "watchers",
+      consumer: "elaine",
+      min_idle: 3600000,
+      start: StreamId::Id(0, 0),
+      count: Some(25),
+    };
+    assert_eq!(
+      format!("{}", cmd),
+      String::from(
+        "*8\r\n$10\r\nXAUTOCLAIM\r\n$8\r\nepisodes\r\n$8\r\nwatchers\r\n$6\r\nelaine\r\n$7\r\n3600000\r\n$3\r\n0-0\r\n$5\r\nCOUNT\r\n$2\r\n25\r\n"
+      )
+    );
+  }
+
+  #[test]
+  fn test_xpending_summary() {
+    let cmd = StreamCommand::Pending::<_, &str> {
+      key: "episodes",
+      group: "watchers",
+      range: None,
+      consumer: None,
+    };
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$8\r\nXPENDING\r\n$8\r\nepisodes\r\n$8\r\nwatchers\r\n")
+    );
+  }
+
+  #[test]
+  fn test_xpending_range() {
+    let cmd = StreamCommand::Pending::<_, &str> {
+      key: "episodes",
+      group: "watchers",
+      range: Some((StreamId::Min, StreamId::Max, 10)),
+      consumer: None,
+    };
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*6\r\n$8\r\nXPENDING\r\n$8\r\nepisodes\r\n$8\r\nwatchers\r\n$1\r\n-\r\n$1\r\n+\r\n$2\r\n10\r\n")
+    );
+  }
+
+  #[test]
+  fn test_xpending_range_consumer() {
+    let cmd = StreamCommand::Pending::<_, &str> {
+      key: "episodes",
+      group: "watchers",
+      range: Some((StreamId::Id(0, 0), StreamId::Max, 10)),
+      consumer: Some("jerry"),
+    };
+    assert_eq!(
+      format!("{}", cmd),
+      String::from(
+        "*7\r\n$8\r\nXPENDING\r\n$8\r\nepisodes\r\n$8\r\nwatchers\r\n$3\r\n0-0\r\n$1\r\n+\r\n$2\r\n10\r\n$5\r\njerry\r\n"
+      )
+    );
+  }
+}
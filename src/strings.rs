@@ -10,6 +10,10 @@ pub enum StringCommand<S, V> {
   /// Returns the value of a key(s).
   Get(Arity<S>),
 
+  /// Returns the value of a key while updating its time to live; `None` will remove the time to
+  /// live entirely (`PERSIST`).
+  GetEx(S, Option<std::time::Duration>),
+
   /// Returns the length of a key.
   Len(S),
 
@@ -19,6 +23,9 @@ pub enum StringCommand<S, V> {
   /// Increments the value stored at a key.
   Incr(S, i64),
 
+  /// Increments the value stored at a key by a floating point amount.
+  IncrFloat(S, f64),
+
   /// Appends a value to a string.
   Append(S, V),
 }
@@ -38,6 +45,12 @@ where
         format_bulk_string(key),
         format_bulk_string(amt)
       ),
+      StringCommand::IncrFloat(key, amt) => write!(
+        formatter,
+        "*3\r\n$11\r\nINCRBYFLOAT\r\n{}{}",
+        format_bulk_string(key),
+        format_bulk_string(amt)
+      ),
       StringCommand::Decr(key, 1) => write!(formatter, "*2\r\n$4\r\nDECR\r\n{}", format_bulk_string(key)),
       StringCommand::Decr(key, amt) => write!(
         formatter,
@@ -46,6 +59,19 @@ where
         format_bulk_string(amt)
       ),
       StringCommand::Get(Arity::One(key)) => write!(formatter, "*2\r\n$3\r\nGET\r\n{}", format_bulk_string(key)),
+      StringCommand::GetEx(key, Some(timeout)) => write!(
+        formatter,
+        "*4\r\n$5\r\nGETEX\r\n{}{}{}",
+        format_bulk_string(key),
+        format_bulk_string("PX"),
+        format_bulk_string(timeout.as_millis())
+      ),
+      StringCommand::GetEx(key, None) => write!(
+        formatter,
+        "*3\r\n$5\r\nGETEX\r\n{}{}",
+        format_bulk_string(key),
+        format_bulk_string("PERSIST")
+      ),
       StringCommand::Get(Arity::Many(keys)) => {
         let count = keys.len();
         let tail = keys.iter().map(format_bulk_string).collect::<String>();
@@ -103,6 +129,33 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_incrbyfloat() {
+    let cmd = StringCommand::IncrFloat::<_, &str>("episodes", -1.5);
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$11\r\nINCRBYFLOAT\r\n$8\r\nepisodes\r\n$4\r\n-1.5\r\n")
+    );
+  }
+
+  #[test]
+  fn test_getex_px() {
+    let cmd = StringCommand::GetEx::<_, &str>("seinfeld", Some(std::time::Duration::from_millis(1500)));
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*4\r\n$5\r\nGETEX\r\n$8\r\nseinfeld\r\n$2\r\nPX\r\n$4\r\n1500\r\n")
+    );
+  }
+
+  #[test]
+  fn test_getex_persist() {
+    let cmd = StringCommand::GetEx::<_, &str>("seinfeld", None);
+    assert_eq!(
+      format!("{}", cmd),
+      String::from("*3\r\n$5\r\nGETEX\r\n$8\r\nseinfeld\r\n$7\r\nPERSIST\r\n")
+    );
+  }
+
   #[test]
   fn test_strlen_present() {
     let cmd = StringCommand::Len::<_, &str>("seinfeld");
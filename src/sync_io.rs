@@ -1,29 +1,49 @@
 #![warn(clippy::print_stdout)]
 
-use crate::response::{readline, Response, ResponseLine, ResponseValue};
+use crate::cluster::{command_key, key_slot, ClusterNodes, Redirect, MAX_REDIRECTS};
+use crate::connection::ConnectionBuilder;
+use crate::modifiers::{Arity, Insertion, Side};
+use crate::response::{
+  command_arity, command_present, float_value, published_message, readline, transaction_value, ArrayAccumulator,
+  Message, Response, ResponseLine, ResponseValue,
+};
+use crate::{Command, HashCommand, ListCommand, StringCommand};
 use std::io::prelude::*;
 use std::io::{Error, ErrorKind};
 
-/// After sending a command, the read here is used to parse the response from our connection
-/// into the response enum.
-pub fn read<C>(read: C) -> Result<Response, Error>
+/// The amount of items requested by each pop while draining a list.
+const DRAIN_BATCH: usize = 1000;
+
+/// Parses a single response from the lines of a buffered reader. Keeping the buffer outside of
+/// this function allows pipelined exchanges to read many responses without losing whatever was
+/// buffered beyond the first one.
+fn read_lines<B>(lines: &mut std::io::Lines<B>) -> Result<Response, Error>
 where
-  C: std::io::Read + std::marker::Unpin,
+  B: std::io::BufRead,
 {
-  let mut lines = std::io::BufReader::new(read).lines();
+  read_tagged_lines(lines).map(|(_, response)| response)
+}
 
-  match lines
+/// Parses a single response, along with the type tag of the first line the server sent.
+fn read_tagged_lines<B>(lines: &mut std::io::Lines<B>) -> Result<(&'static str, Response), Error>
+where
+  B: std::io::BufRead,
+{
+  let line = lines
     .next()
     .ok_or_else(|| Error::new(ErrorKind::NotFound, "kramer: No lines available from reader."))
-    .and_then(|opt| opt.and_then(readline))
-  {
-    Ok(ResponseLine::Array(size)) => {
-      let mut store = Vec::with_capacity(size);
+    .and_then(|opt| opt.and_then(readline))?;
+  let tag = line.type_tag();
 
+  let response = match line {
+    ResponseLine::Array(size) => {
       if size == 0 {
-        return Ok(Response::Array(vec![]));
+        return Ok((tag, Response::Array(vec![])));
       }
 
+      let mut accumulator = ArrayAccumulator::new(size);
+      let mut failure = None;
+
       while let Ok(kind) = lines
         .next()
         .ok_or_else(|| {
@@ -34,45 +54,71 @@ where
         })
         .and_then(|opt| opt.and_then(readline))
       {
-        match kind {
+        let complete = match kind {
           ResponseLine::BulkString(size) => match lines.next() {
-            Some(Ok(bulky)) if bulky.len() == size => {
-              store.push(ResponseValue::String(bulky));
-            }
+            Some(Ok(bulky)) if bulky.len() == size => accumulator.push(ResponseValue::String(bulky)),
             _ => break,
           },
-          _ => break,
-        }
+          ResponseLine::SimpleString(simple) => accumulator.push(ResponseValue::String(simple)),
+          ResponseLine::Integer(value) => accumulator.push(ResponseValue::Integer(value)),
+          ResponseLine::Null => accumulator.push(ResponseValue::Empty),
+          ResponseLine::Array(inner) => accumulator.open(inner),
+          // The remaining elements are still read to leave the connection usable; the first error
+          // is returned once the array is complete.
+          ResponseLine::Error(message) => {
+            failure.get_or_insert(message);
+            accumulator.push(ResponseValue::Empty)
+          }
+        };
 
-        if store.len() >= size {
-          return Ok(Response::Array(store));
+        if let Some(store) = complete {
+          return match failure {
+            Some(message) => Err(Error::other(message)),
+            None => Ok((tag, Response::Array(store))),
+          };
         }
       }
 
-      if size != store.len() {
-        let message = format!("expected {} elements in response and received {}", size, store.len());
-        return Err(Error::new(ErrorKind::InvalidData, message));
-      }
-
-      Ok(Response::Array(store))
+      let message = format!(
+        "expected {} elements in response and received {}",
+        size,
+        accumulator.received()
+      );
+      Err(Error::new(ErrorKind::InvalidData, message))
     }
-    Ok(ResponseLine::BulkString(size)) => {
-      if size < 1 {
-        return Ok(Response::Item(ResponseValue::Empty));
-      }
-
-      let out = lines
-        .next()
-        .ok_or_else(|| Error::new(ErrorKind::Other, "no line to work with"))??;
+    ResponseLine::BulkString(_) => {
+      let out = lines.next().ok_or_else(|| Error::other("no line to work with"))??;
 
       Ok(Response::Item(ResponseValue::String(out)))
     }
-    Ok(ResponseLine::Null) => Ok(Response::Item(ResponseValue::Empty)),
-    Ok(ResponseLine::SimpleString(simple)) => Ok(Response::Item(ResponseValue::String(simple))),
-    Ok(ResponseLine::Integer(value)) => Ok(Response::Item(ResponseValue::Integer(value))),
-    Ok(ResponseLine::Error(e)) => Err(Error::new(ErrorKind::Other, e)),
-    Err(e) => Err(e),
-  }
+    ResponseLine::Null => Ok(Response::Item(ResponseValue::Empty)),
+    ResponseLine::SimpleString(simple) => Ok(Response::Item(ResponseValue::String(simple))),
+    ResponseLine::Integer(value) => Ok(Response::Item(ResponseValue::Integer(value))),
+    ResponseLine::Error(e) => Err(Error::other(e)),
+  };
+
+  response.map(|response| (tag, response))
+}
+
+/// After sending a command, the read here is used to parse the response from our connection
+/// into the response enum.
+pub fn read<C>(read: C) -> Result<Response, Error>
+where
+  C: std::io::Read + std::marker::Unpin,
+{
+  let mut lines = std::io::BufReader::new(read).lines();
+  read_lines(&mut lines)
+}
+
+/// Reads a response along with the type tag of the first line the server sent (see
+/// `ResponseLine::type_tag`). Unlike the parsed `Response`, the tag distinguishes simple string
+/// replies such as `+OK` from bulk string replies.
+pub fn read_tagged<C>(read: C) -> Result<(&'static str, Response), Error>
+where
+  C: std::io::Read + std::marker::Unpin,
+{
+  let mut lines = std::io::BufReader::new(read).lines();
+  read_tagged_lines(&mut lines)
 }
 
 /// Writes a command to the connection and will attempt to read a response.
@@ -93,3 +139,257 @@ where
   let mut stream = std::net::TcpStream::connect(addr)?;
   execute(&mut stream, message)
 }
+
+impl ConnectionBuilder {
+  /// Opens a connection to the address with the configured socket options applied.
+  pub fn connect(&self) -> Result<std::net::TcpStream, Error> {
+    let stream = std::net::TcpStream::connect(self.addr.as_str())?;
+    self.configure(socket2::SockRef::from(&stream))?;
+    Ok(stream)
+  }
+}
+
+/// Sets every field of a hash by splitting the fields into `HSET` commands of at most `chunk`
+/// fields each. The commands are pipelined; all of them are written before any response is read.
+/// The returned value is the total amount of fields that were newly added to the hash.
+pub fn hset_all<C>(mut connection: C, key: &str, fields: &[(&str, &str)], chunk: usize) -> Result<i64, Error>
+where
+  C: std::io::Write + std::io::Read + std::marker::Unpin,
+{
+  if chunk == 0 {
+    return Err(Error::new(
+      ErrorKind::InvalidInput,
+      "kramer: hset_all chunk size must be non-zero",
+    ));
+  }
+
+  let commands = fields
+    .chunks(chunk)
+    .map(|pairs| {
+      format!(
+        "{}",
+        HashCommand::Set(key, Arity::Many(pairs.to_vec()), Insertion::Always)
+      )
+    })
+    .collect::<Vec<String>>();
+
+  connection.write_all(commands.concat().as_bytes())?;
+
+  let mut lines = std::io::BufReader::new(connection).lines();
+  let mut outcome = Ok(0);
+
+  // Every response must be consumed, even after a failure, to leave the connection in a usable state.
+  for _ in 0..commands.len() {
+    let response = read_lines(&mut lines);
+
+    outcome = match (outcome, response) {
+      (Ok(total), Ok(Response::Item(ResponseValue::Integer(added)))) => Ok(total + added),
+      (Ok(_), Ok(other)) => Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("kramer: unexpected HSET response {:?}", other),
+      )),
+      (Ok(_), Err(e)) => Err(e),
+      (Err(e), _) => Err(e),
+    };
+  }
+
+  outcome
+}
+
+/// Subscribes the connection to the provided channels and invokes the handler with every message
+/// published to them. This will block until the handler returns `ControlFlow::Break`, whose value
+/// is then returned. Note that the connection is _not_ unsubscribed before returning.
+pub fn subscribe_with<C, S, F, B>(mut connection: C, channels: Arity<S>, mut handler: F) -> Result<B, Error>
+where
+  S: std::fmt::Display,
+  C: std::io::Write + std::io::Read + std::marker::Unpin,
+  F: FnMut(Message) -> std::ops::ControlFlow<B>,
+{
+  write!(connection, "{}", Command::Subscribe::<S, &str>(channels))?;
+
+  let mut lines = std::io::BufReader::new(connection).lines();
+
+  loop {
+    if let Some(message) = published_message(read_lines(&mut lines)?) {
+      if let std::ops::ControlFlow::Break(value) = handler(message) {
+        return Ok(value);
+      }
+    }
+  }
+}
+
+/// Increments the value stored at a key by a floating point amount, returning the new value.
+pub fn incr_float<C, S>(connection: C, key: S, by: f64) -> Result<f64, Error>
+where
+  S: std::fmt::Display,
+  C: std::io::Write + std::io::Read + std::marker::Unpin,
+{
+  float_value(execute(connection, StringCommand::IncrFloat::<S, &str>(key, by))?)
+}
+
+/// Increments a field of the hash stored at a key by a floating point amount, returning the new
+/// value.
+pub fn hincr_float<C, S>(connection: C, key: S, field: S, by: f64) -> Result<f64, Error>
+where
+  S: std::fmt::Display,
+  C: std::io::Write + std::io::Read + std::marker::Unpin,
+{
+  float_value(execute(connection, HashCommand::IncrFloat::<S, &str>(key, field, by))?)
+}
+
+/// Returns the value stored at a key while updating its time to live. Servers that support `GETEX`
+/// (6.2 and later) receive that single command, while older servers receive a `GET` and expiry
+/// within a transaction. Support is determined by a `COMMAND INFO` lookup whose result is stored
+/// in `getex_support`; providing the same value to later calls will skip the lookup.
+/// Time to lives shorter than a millisecond are rejected with `ErrorKind::InvalidInput`.
+///
+/// Commands queued in a transaction are applied independently, so when the `GET` of the fallback
+/// fails (for example, because the key does not hold a string) the error is returned but the
+/// expiry has still been applied. `GETEX` leaves the time to live untouched in that case.
+pub fn get_and_extend<C, S>(
+  mut connection: C,
+  key: S,
+  ttl: std::time::Duration,
+  getex_support: &mut Option<bool>,
+) -> Result<Response, Error>
+where
+  S: std::fmt::Display,
+  C: std::io::Write + std::io::Read + std::marker::Unpin,
+{
+  // Neither `GETEX key PX 0` nor `PEXPIRE key 0` leave the key in place; reject these up front so
+  // both paths behave the same.
+  if ttl.as_millis() == 0 {
+    return Err(Error::new(
+      ErrorKind::InvalidInput,
+      "kramer: get_and_extend ttl must be at least one millisecond",
+    ));
+  }
+
+  let supported = match getex_support {
+    Some(supported) => *supported,
+    None => {
+      let info = execute(&mut connection, Command::CommandInfo::<_, &str>(Arity::One("GETEX")))?;
+      *getex_support.insert(command_present(&info))
+    }
+  };
+
+  if supported {
+    return execute(connection, StringCommand::GetEx::<_, &str>(key, Some(ttl)));
+  }
+
+  let transaction = format!(
+    "{}{}{}{}",
+    Command::Multi::<&str, &str>,
+    StringCommand::Get::<_, &str>(Arity::One(&key)),
+    Command::Expire::<_, &str>(&key, ttl, None),
+    Command::Exec::<&str, &str>
+  );
+  connection.write_all(transaction.as_bytes())?;
+
+  let mut lines = std::io::BufReader::new(connection).lines();
+
+  // `MULTI` and each queued command are acknowledged before `EXEC` returns the actual replies.
+  let acknowledgements = (0..3).map(|_| read_lines(&mut lines)).collect::<Vec<_>>();
+  let replies = read_lines(&mut lines);
+
+  for acknowledgement in acknowledgements {
+    acknowledgement?;
+  }
+
+  transaction_value(replies?)
+}
+
+/// Sends a command to the cluster node serving the slot of its key. When that node replies with a
+/// `MOVED` redirect, the slot map is updated and the command is retried on the indicated node, up
+/// to `MAX_REDIRECTS` times. Commands without a key are sent to the node serving slot `0`.
+///
+/// Every attempt opens a new connection to the node; connections are not kept between calls.
+/// Slots that have not yet been learned from a redirect are sent to the first node, so the first
+/// command for each slot may take an extra round trip.
+pub fn execute_cluster<S>(nodes: &mut ClusterNodes, message: S) -> Result<Response, Error>
+where
+  S: std::fmt::Display,
+{
+  let message = format!("{}", message);
+  let slot = command_key(&message).map(|key| key_slot(&key)).unwrap_or_default();
+  let mut redirects = 0;
+
+  loop {
+    let addr = nodes
+      .node_for(slot)
+      .map(String::from)
+      .ok_or_else(|| Error::new(ErrorKind::NotFound, "kramer: no cluster nodes available"))?;
+
+    match send(addr.as_str(), message.as_str()) {
+      Err(error) if redirects < MAX_REDIRECTS => match Redirect::parse(&error.to_string()) {
+        Some(redirect) => {
+          nodes.assign(redirect);
+          redirects += 1;
+        }
+        None => return Err(error),
+      },
+      result => return result,
+    }
+  }
+}
+
+/// Pops every item from the side of a list until it is empty, returning the items in the order
+/// they were popped. Items are popped many at a time where supported (redis 6.2 and later); older
+/// servers fall back to popping a single item at a time. Support is determined by a `COMMAND INFO`
+/// lookup whose result is stored in `counted_pops`; providing the same value to later calls will
+/// skip the lookup.
+pub fn drain_list<C, S>(
+  mut connection: C,
+  key: S,
+  side: Side,
+  counted_pops: &mut Option<bool>,
+) -> Result<Vec<String>, Error>
+where
+  S: std::fmt::Display,
+  C: std::io::Write + std::io::Read + std::marker::Unpin,
+{
+  let counted = match counted_pops {
+    Some(counted) => *counted,
+    None => {
+      let name = match side {
+        Side::Left => "LPOP",
+        Side::Right => "RPOP",
+      };
+      let info = execute(&mut connection, Command::CommandInfo::<_, &str>(Arity::One(name)))?;
+      // Accepting a count changed the arity of the pop commands from 2 to -2.
+      *counted_pops.insert(command_arity(&info).is_some_and(|arity| arity < 0))
+    }
+  };
+  let mut drained = vec![];
+
+  loop {
+    let response = if counted {
+      execute(
+        &mut connection,
+        ListCommand::PopMany::<_, &str>(side.clone(), &key, DRAIN_BATCH),
+      )?
+    } else {
+      execute(&mut connection, ListCommand::Pop::<_, &str>(side.clone(), &key, None))?
+    };
+
+    match response {
+      Response::Item(ResponseValue::Empty) => return Ok(drained),
+      Response::Item(ResponseValue::String(item)) => drained.push(item),
+      Response::Array(items) => {
+        for item in items {
+          match item {
+            ResponseValue::String(item) => drained.push(item),
+            other => {
+              let message = format!("kramer: unexpected list item {:?}", other);
+              return Err(Error::new(ErrorKind::InvalidData, message));
+            }
+          }
+        }
+      }
+      other => {
+        let message = format!("kramer: unexpected pop response {:?}", other);
+        return Err(Error::new(ErrorKind::InvalidData, message));
+      }
+    }
+  }
+}
@@ -5,8 +5,9 @@ extern crate kramer;
 use async_std::prelude::*;
 
 use kramer::{
-  execute, read, send, Arity, Command, HashCommand, Insertion, ListCommand, Response, ResponseValue, Side,
-  StringCommand,
+  drain_list, execute, execute_cluster, get_and_extend, hincr_float, hset_all, incr_float, key_slot, read, read_tagged,
+  send, subscribe_with, Arity, ClusterNodes, Command, ConnectionBuilder, HashCommand, Insertion, ListCommand, Response,
+  ResponseValue, Side, StreamCommand, StreamId, StringCommand,
 };
 use std::env::var;
 
@@ -27,6 +28,38 @@ fn get_redis_url() -> String {
   format!("{}:{}", host, port)
 }
 
+/// Starts a server accepting a single connection that answers every command it receives with the
+/// reply produced by `respond`. The returned handle resolves to the received commands, with their
+/// arguments joined by spaces, once the connection has been closed.
+fn mock_server<F, R>(respond: F) -> (String, std::thread::JoinHandle<Vec<String>>)
+where
+  F: Fn(&[String]) -> R + Send + 'static,
+  R: std::fmt::Display,
+{
+  use std::io::{BufRead, Write};
+
+  let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bound");
+  let addr = listener.local_addr().expect("address").to_string();
+
+  let handle = std::thread::spawn(move || {
+    let (stream, _) = listener.accept().expect("accepted");
+    let mut writer = stream.try_clone().expect("cloned");
+    let mut lines = std::io::BufReader::new(stream).lines().map(|line| line.expect("line"));
+    let mut received = vec![];
+
+    while let Some(header) = lines.next() {
+      let count = header.trim_start_matches('*').parse::<usize>().expect("array header");
+      let args = (0..count).filter_map(|_| lines.nth(1)).collect::<Vec<String>>();
+      write!(writer, "{}", respond(&args)).expect("replied");
+      received.push(args.join(" "));
+    }
+
+    received
+  });
+
+  (addr, handle)
+}
+
 #[test]
 fn test_echo() {
   let url = get_redis_url();
@@ -1365,3 +1398,533 @@ fn test_linsert_right_present() {
     ])
   );
 }
+
+#[test]
+fn test_hset_all_chunked() {
+  let (key, url) = ("test_hset_all_chunked", get_redis_url());
+  let owned = (0..2000)
+    .map(|i| (format!("field-{}", i), format!("value-{}", i)))
+    .collect::<Vec<(String, String)>>();
+  let fields = owned
+    .iter()
+    .map(|(f, v)| (f.as_str(), v.as_str()))
+    .collect::<Vec<(&str, &str)>>();
+
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(url.as_str()).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    let added = hset_all(&mut stream, key, &fields, 500).await?;
+    let len = execute(&mut stream, Command::Hashes::<_, &str>(HashCommand::Len(key))).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    Ok::<_, std::io::Error>((added, len))
+  });
+
+  assert_eq!(result.unwrap(), (2000, Response::Item(ResponseValue::Integer(2000))));
+}
+
+#[test]
+fn test_subscribe_with() {
+  let (channel, url) = ("test_subscribe_with", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    let subscriber = async_std::net::TcpStream::connect(url.as_str()).await?;
+    let publish_url = url.clone();
+
+    let publisher = async_std::task::spawn(async move {
+      // Messages published before the subscription is in place are dropped; keep sending the first
+      // one until it has been received by somebody.
+      while send(publish_url.as_str(), Command::Publish(channel, "one")).await?
+        == Response::Item(ResponseValue::Integer(0))
+      {
+        async_std::task::sleep(std::time::Duration::from_millis(10)).await;
+      }
+
+      send(publish_url.as_str(), Command::Publish(channel, "two")).await?;
+      send(publish_url.as_str(), Command::Publish(channel, "three")).await
+    });
+
+    let mut received = vec![];
+    subscribe_with(subscriber, Arity::One(channel), |message| {
+      received.push(message.payload);
+
+      if received.len() == 3 {
+        std::ops::ControlFlow::Break(())
+      } else {
+        std::ops::ControlFlow::Continue(())
+      }
+    })
+    .await?;
+
+    publisher.await?;
+    Ok::<_, std::io::Error>(received)
+  });
+
+  assert_eq!(result.unwrap(), vec!["one", "two", "three"]);
+}
+
+#[test]
+fn test_incr_float() {
+  let (key, url) = ("test_incr_float", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(url.as_str()).await?;
+    let set = StringCommand::Set(Arity::One((key, "10.50")), None, Insertion::Always);
+    execute(&mut stream, set).await?;
+    let up = incr_float(&mut stream, key, 0.1).await?;
+    let down = incr_float(&mut stream, key, -5.0).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    Ok::<_, std::io::Error>((up, down))
+  });
+
+  assert_eq!(result.unwrap(), (10.6, 5.6));
+}
+
+#[test]
+fn test_hincr_float() {
+  let (key, url) = ("test_hincr_float", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(url.as_str()).await?;
+    execute(&mut stream, set_field(key, "field", "10.50")).await?;
+    let up = hincr_float(&mut stream, key, "field", 0.1).await?;
+    let down = hincr_float(&mut stream, key, "field", -5.0).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    Ok::<_, std::io::Error>((up, down))
+  });
+
+  assert_eq!(result.unwrap(), (10.6, 5.6));
+}
+
+#[test]
+fn test_get_and_extend() {
+  let (key, url) = ("test_get_and_extend", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(url.as_str()).await?;
+    let set = StringCommand::Set(Arity::One((key, "kramer")), None, Insertion::Always);
+    execute(&mut stream, set).await?;
+    let mut support = None;
+    let ttl = std::time::Duration::from_millis(1500);
+    let value = get_and_extend(&mut stream, key, ttl, &mut support).await?;
+    // There is no TTL command enum; PTTL is written out by hand.
+    let pttl = execute(&mut stream, "*2\r\n$4\r\nPTTL\r\n$19\r\ntest_get_and_extend\r\n").await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    Ok::<_, std::io::Error>((support, value, pttl))
+  });
+
+  let (support, value, pttl) = result.unwrap();
+  assert_eq!(support, Some(true));
+  assert_eq!(value, Response::Item(ResponseValue::String("kramer".into())));
+  assert!(
+    matches!(pttl, Response::Item(ResponseValue::Integer(ms)) if ms > 0 && ms <= 1500),
+    "unexpected PTTL response {:?}",
+    pttl
+  );
+}
+
+#[test]
+fn test_push_order_left() {
+  let (key, url) = ("test_push_order_left", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    send(url.as_str(), Command::Del::<_, &str>(Arity::One(key))).await?;
+    let push = Command::Lists::<_, &str>(ListCommand::Push(
+      (Side::Left, Insertion::Always),
+      key,
+      Arity::Many(vec!["a", "b", "c"]),
+    ));
+    send(url.as_str(), push).await?;
+    let out = send(url.as_str(), Command::Lists::<_, &str>(ListCommand::Range(key, 0, -1))).await;
+    send(url.as_str(), Command::Del::<_, &str>(Arity::One(key))).await?;
+    out
+  });
+
+  assert_eq!(
+    result.unwrap(),
+    Response::Array(vec![
+      ResponseValue::String(String::from("c")),
+      ResponseValue::String(String::from("b")),
+      ResponseValue::String(String::from("a")),
+    ])
+  );
+}
+
+#[test]
+fn test_push_order_right() {
+  let (key, url) = ("test_push_order_right", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    send(url.as_str(), Command::Del::<_, &str>(Arity::One(key))).await?;
+    let push = Command::Lists::<_, &str>(ListCommand::Push(
+      (Side::Right, Insertion::Always),
+      key,
+      Arity::Many(vec!["a", "b", "c"]),
+    ));
+    send(url.as_str(), push).await?;
+    let out = send(url.as_str(), Command::Lists::<_, &str>(ListCommand::Range(key, 0, -1))).await;
+    send(url.as_str(), Command::Del::<_, &str>(Arity::One(key))).await?;
+    out
+  });
+
+  assert_eq!(
+    result.unwrap(),
+    Response::Array(vec![
+      ResponseValue::String(String::from("a")),
+      ResponseValue::String(String::from("b")),
+      ResponseValue::String(String::from("c")),
+    ])
+  );
+}
+
+#[test]
+fn test_connection_builder_nodelay() {
+  let url = get_redis_url();
+
+  let (nodelay, result) = async_std::task::block_on(async {
+    let mut con = ConnectionBuilder::new(url).nodelay(true).connect().await?;
+    let nodelay = con.nodelay()?;
+    execute(&mut con, Command::Echo::<_, &str>("hello"))
+      .await
+      .map(|result| (nodelay, result))
+  })
+  .expect("executed");
+
+  assert!(nodelay);
+  assert_eq!(result, Response::Item(ResponseValue::String("hello".into())));
+}
+
+#[test]
+fn test_read_empty_bulk_string() {
+  let result = async_std::task::block_on(read(&b"$0\r\n\r\n"[..]));
+  assert_eq!(result.unwrap(), Response::Item(ResponseValue::String(String::new())));
+}
+
+#[test]
+fn test_read_tagged_simple_and_bulk() {
+  let (simple, bulk) = async_std::task::block_on(async {
+    let simple = read_tagged(&b"+OK\r\n"[..]).await?;
+    let bulk = read_tagged(&b"$2\r\nOK\r\n"[..]).await?;
+    Ok::<_, std::io::Error>((simple, bulk))
+  })
+  .expect("read");
+  assert_eq!(
+    simple,
+    ("simple-string", Response::Item(ResponseValue::String("OK".into())))
+  );
+  assert_eq!(
+    bulk,
+    ("bulk-string", Response::Item(ResponseValue::String("OK".into())))
+  );
+}
+
+#[test]
+fn test_get_and_extend_fallback_mock() {
+  let (addr, server) = mock_server(|args| match args[0].as_str() {
+    "COMMAND" => "*1\r\n*-1\r\n",
+    "MULTI" => "+OK\r\n",
+    "EXEC" => "*2\r\n$6\r\nkramer\r\n:1\r\n",
+    _ => "+QUEUED\r\n",
+  });
+  let mut support = None;
+
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(addr).await?;
+    let ttl = std::time::Duration::from_millis(1500);
+    get_and_extend(&mut stream, "seinfeld", ttl, &mut support).await
+  });
+
+  let commands = server.join().expect("served");
+  assert_eq!(support, Some(false));
+  assert_eq!(result.unwrap(), Response::Item(ResponseValue::String("kramer".into())));
+  assert_eq!(
+    commands,
+    vec![
+      "COMMAND INFO GETEX",
+      "MULTI",
+      "GET seinfeld",
+      "PEXPIRE seinfeld 1500",
+      "EXEC"
+    ]
+  );
+}
+
+#[test]
+fn test_get_and_extend_fallback_wrongtype_mock() {
+  let (addr, server) = mock_server(|args| match args[0].as_str() {
+    "COMMAND" => "*1\r\n*-1\r\n",
+    "MULTI" => "+OK\r\n",
+    "EXEC" => "*2\r\n-WRONGTYPE Operation against a key holding the wrong kind of value\r\n:1\r\n",
+    _ => "+QUEUED\r\n",
+  });
+  let ttl = std::time::Duration::from_millis(1500);
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(addr).await?;
+    get_and_extend(&mut stream, "seinfeld", ttl, &mut None).await
+  });
+  let commands = server.join().expect("served");
+  let error = result.expect_err("failed");
+  assert_eq!(error.kind(), std::io::ErrorKind::Other);
+  assert!(error.to_string().contains("WRONGTYPE"));
+  assert_eq!(
+    commands,
+    vec![
+      "COMMAND INFO GETEX",
+      "MULTI",
+      "GET seinfeld",
+      "PEXPIRE seinfeld 1500",
+      "EXEC"
+    ]
+  );
+}
+
+#[test]
+fn test_get_and_extend_empty_value_mock() {
+  let modern = "*1\r\n*3\r\n$5\r\ngetex\r\n:-2\r\n*1\r\n+write\r\n";
+  let results = [modern, "*1\r\n*-1\r\n"]
+    .iter()
+    .map(|info| {
+      let info = *info;
+      let (addr, server) = mock_server(move |args| match args[0].as_str() {
+        "COMMAND" => info,
+        "GETEX" => "$0\r\n\r\n",
+        "MULTI" => "+OK\r\n",
+        "EXEC" => "*2\r\n$0\r\n\r\n:1\r\n",
+        _ => "+QUEUED\r\n",
+      });
+      let result = async_std::task::block_on(async {
+        let mut stream = async_std::net::TcpStream::connect(addr).await?;
+        let ttl = std::time::Duration::from_millis(1500);
+        get_and_extend(&mut stream, "seinfeld", ttl, &mut None).await
+      });
+      server.join().expect("served");
+      result.unwrap()
+    })
+    .collect::<Vec<Response>>();
+  let empty = || Response::Item(ResponseValue::String(String::new()));
+  assert_eq!(results, vec![empty(), empty()]);
+}
+
+#[test]
+fn test_get_and_extend_rejects_sub_millisecond_ttl() {
+  let mut stream = async_std::io::Cursor::new(Vec::new());
+  let ttl = std::time::Duration::from_micros(500);
+  let result = async_std::task::block_on(get_and_extend(&mut stream, "seinfeld", ttl, &mut Some(false)));
+  assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+  assert!(stream.into_inner().is_empty());
+}
+
+#[test]
+fn test_xautoclaim_idle() {
+  let (key, group, url) = ("test_xautoclaim_idle", "test_xautoclaim_idle_group", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(url.as_str()).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    let create = StreamCommand::CreateGroup::<_, &str>(key, group, StreamId::Id(0, 0));
+    execute(&mut stream, create).await?;
+    let id = execute(
+      &mut stream,
+      StreamCommand::Add(key, None, Arity::One(("name", "kramer"))),
+    )
+    .await?;
+    let read = StreamCommand::ReadGroup::<_, &str> {
+      key,
+      group,
+      consumer: "jerry",
+      count: Some(1),
+    };
+    execute(&mut stream, read).await?;
+    async_std::task::sleep(std::time::Duration::from_millis(50)).await;
+    let claim = StreamCommand::AutoClaim::<_, &str> {
+      key,
+      group,
+      consumer: "elaine",
+      min_idle: 10,
+      start: StreamId::Id(0, 0),
+      count: Some(10),
+    };
+    let claimed = execute(&mut stream, claim).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    Ok::<_, std::io::Error>((id, claimed))
+  });
+
+  let (id, claimed) = match result.unwrap() {
+    (Response::Item(ResponseValue::String(id)), Response::Array(parts)) => (id, parts),
+    other => panic!("unexpected XADD/XAUTOCLAIM responses {:?}", other),
+  };
+  let mut parts = claimed.into_iter();
+
+  // Redis 7 includes a third element listing deleted entries.
+  assert_eq!(parts.next(), Some(ResponseValue::String("0-0".into())));
+  assert_eq!(
+    parts.next(),
+    Some(ResponseValue::Array(vec![ResponseValue::Array(vec![
+      ResponseValue::String(id),
+      ResponseValue::Array(vec![
+        ResponseValue::String("name".into()),
+        ResponseValue::String("kramer".into()),
+      ]),
+    ])]))
+  );
+}
+
+#[test]
+fn test_xpending_summary() {
+  let (key, group, url) = ("test_xpending_summary", "test_xpending_summary_group", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(url.as_str()).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    let create = StreamCommand::CreateGroup::<_, &str>(key, group, StreamId::Id(0, 0));
+    execute(&mut stream, create).await?;
+    let id = execute(
+      &mut stream,
+      StreamCommand::Add(key, None, Arity::One(("name", "kramer"))),
+    )
+    .await?;
+    let read = StreamCommand::ReadGroup::<_, &str> {
+      key,
+      group,
+      consumer: "jerry",
+      count: Some(1),
+    };
+    execute(&mut stream, read).await?;
+    let pending = StreamCommand::Pending::<_, &str> {
+      key,
+      group,
+      range: None,
+      consumer: None,
+    };
+    let summary = execute(&mut stream, pending).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    Ok::<_, std::io::Error>((id, summary))
+  });
+
+  let (id, summary) = match result.unwrap() {
+    (Response::Item(ResponseValue::String(id)), summary) => (id, summary),
+    other => panic!("unexpected XADD response {:?}", other),
+  };
+  assert_eq!(
+    summary,
+    Response::Array(vec![
+      ResponseValue::Integer(1),
+      ResponseValue::String(id.clone()),
+      ResponseValue::String(id),
+      ResponseValue::Array(vec![ResponseValue::Array(vec![
+        ResponseValue::String("jerry".into()),
+        ResponseValue::String("1".into()),
+      ])]),
+    ])
+  );
+}
+
+#[test]
+fn test_execute_cluster_moved_mock() {
+  let (right, right_server) = mock_server(|_| "$6\r\nkramer\r\n");
+  let moved = format!("-MOVED {} {}\r\n", key_slot("seinfeld"), right);
+  let (wrong, wrong_server) = mock_server(move |_| moved.clone());
+  let mut nodes = ClusterNodes::new(vec![wrong.clone(), right.clone()]);
+  let result = async_std::task::block_on(execute_cluster(
+    &mut nodes,
+    StringCommand::Get::<_, &str>(Arity::One("seinfeld")),
+  ));
+  assert_eq!(result.unwrap(), Response::Item(ResponseValue::String("kramer".into())));
+  assert_eq!(nodes.node_for(key_slot("seinfeld")), Some(right.as_str()));
+  assert_eq!(wrong_server.join().expect("served").len(), 1);
+  assert_eq!(right_server.join().expect("served").len(), 1);
+}
+
+#[test]
+fn test_lpos_rank() {
+  let (key, url) = ("test_lpos_rank", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(url.as_str()).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    let push = ListCommand::Push(
+      (Side::Right, Insertion::Always),
+      key,
+      Arity::Many(vec!["kramer", "jerry", "kramer", "elaine", "kramer", "george"]),
+    );
+    execute(&mut stream, push).await?;
+    let second = execute(&mut stream, ListCommand::Pos(key, "kramer", Some(2), None)).await?;
+    let last = execute(&mut stream, ListCommand::Pos(key, "kramer", Some(-1), None)).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    Ok::<_, std::io::Error>((second, last))
+  });
+
+  assert_eq!(
+    result.unwrap(),
+    (
+      Response::Item(ResponseValue::Integer(2)),
+      Response::Item(ResponseValue::Integer(4))
+    )
+  );
+}
+
+#[test]
+fn test_drain_list_right() {
+  let (key, url) = ("test_drain_list_right", get_redis_url());
+
+  let result = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(url.as_str()).await?;
+    execute(&mut stream, Command::Del::<_, &str>(Arity::One(key))).await?;
+    let push = ListCommand::Push(
+      (Side::Right, Insertion::Always),
+      key,
+      Arity::Many(vec!["kramer", "jerry", "elaine", "george"]),
+    );
+    execute(&mut stream, push).await?;
+    let drained = drain_list(&mut stream, key, Side::Right, &mut None).await?;
+    let exists = execute(&mut stream, Command::Exists::<_, &str>(Arity::One(key))).await?;
+    Ok::<_, std::io::Error>((drained, exists))
+  });
+
+  assert_eq!(
+    result.unwrap(),
+    (
+      vec![
+        String::from("george"),
+        String::from("elaine"),
+        String::from("jerry"),
+        String::from("kramer")
+      ],
+      Response::Item(ResponseValue::Integer(0))
+    )
+  );
+}
+
+#[test]
+fn test_drain_list_fallback_mock() {
+  let remaining = std::sync::Mutex::new(vec!["kramer", "", "jerry"]);
+  let (addr, server) = mock_server(move |args| {
+    if args[0] == "COMMAND" {
+      return String::from("*1\r\n*2\r\n$4\r\nrpop\r\n:2\r\n");
+    }
+
+    match remaining.lock().expect("lock").pop() {
+      Some(item) => format!("${}\r\n{}\r\n", item.len(), item),
+      None => String::from("$-1\r\n"),
+    }
+  });
+  let mut counted = None;
+
+  let drained = async_std::task::block_on(async {
+    let mut stream = async_std::net::TcpStream::connect(addr).await?;
+    drain_list(&mut stream, "seinfeld", Side::Right, &mut counted).await
+  });
+
+  let commands = server.join().expect("served");
+  assert_eq!(counted, Some(false));
+  assert_eq!(drained.unwrap(), vec!["jerry", "", "kramer"]);
+  assert_eq!(
+    commands,
+    vec![
+      "COMMAND INFO RPOP",
+      "RPOP seinfeld",
+      "RPOP seinfeld",
+      "RPOP seinfeld",
+      "RPOP seinfeld"
+    ]
+  );
+}
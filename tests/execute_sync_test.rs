@@ -1,8 +1,13 @@
 #![cfg(not(feature = "kramer-async"))]
 extern crate kramer;
 
-use kramer::{execute, Arity, AuthCredentials, Command, Insertion, Response, ResponseValue, SetCommand, StringCommand};
+use kramer::{
+  drain_list, execute, execute_cluster, get_and_extend, hincr_float, hset_all, incr_float, key_slot, read, read_tagged,
+  subscribe_with, Arity, AuthCredentials, ClusterNodes, Command, ConnectionBuilder, HashCommand, Insertion,
+  ListCommand, Message, Response, ResponseValue, SetCommand, Side, StreamCommand, StreamId, StringCommand,
+};
 use std::env::var;
+use std::io::{BufRead, Write};
 
 #[cfg(feature = "acl")]
 use kramer::{AclCommand, SetUser};
@@ -14,6 +19,36 @@ fn get_redis_url() -> String {
   format!("{}:{}", host, port)
 }
 
+/// Starts a server accepting a single connection that answers every command it receives with the
+/// reply produced by `respond`. The returned handle resolves to the received commands, with their
+/// arguments joined by spaces, once the connection has been closed.
+fn mock_server<F, R>(respond: F) -> (String, std::thread::JoinHandle<Vec<String>>)
+where
+  F: Fn(&[String]) -> R + Send + 'static,
+  R: std::fmt::Display,
+{
+  let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bound");
+  let addr = listener.local_addr().expect("address").to_string();
+
+  let handle = std::thread::spawn(move || {
+    let (stream, _) = listener.accept().expect("accepted");
+    let mut writer = stream.try_clone().expect("cloned");
+    let mut lines = std::io::BufReader::new(stream).lines().map(|line| line.expect("line"));
+    let mut received = vec![];
+
+    while let Some(header) = lines.next() {
+      let count = header.trim_start_matches('*').parse::<usize>().expect("array header");
+      let args = (0..count).filter_map(|_| lines.nth(1)).collect::<Vec<String>>();
+      write!(writer, "{}", respond(&args)).expect("replied");
+      received.push(args.join(" "));
+    }
+
+    received
+  });
+
+  (addr, handle)
+}
+
 // TODO: figure out how to run this in CI; would need to consider how to set password without potentially affecting
 // other tests. Might consider a second redis container with auth configured.
 #[test]
@@ -258,3 +293,524 @@ fn test_inter_some() {
     Response::Array(vec![ResponseValue::String(String::from("one"))])
   );
 }
+
+#[test]
+fn test_hset_all_chunked() {
+  let key = "test_hset_all_chunked";
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  let owned = (0..2000)
+    .map(|i| (format!("field-{}", i), format!("value-{}", i)))
+    .collect::<Vec<(String, String)>>();
+  let fields = owned
+    .iter()
+    .map(|(f, v)| (f.as_str(), v.as_str()))
+    .collect::<Vec<(&str, &str)>>();
+  let added = hset_all(&mut con, key, &fields, 500).expect("executed");
+  let len = execute(&mut con, HashCommand::Len::<_, &str>(key)).expect("executed");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  assert_eq!(added, 2000);
+  assert_eq!(len, Response::Item(ResponseValue::Integer(2000)));
+}
+
+#[test]
+fn test_subscribe_with() {
+  let channel = "test_subscribe_with";
+  let subscriber = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+
+  let publisher = std::thread::spawn(move || {
+    let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+
+    // Messages published before the subscription is in place are dropped; keep sending the first
+    // one until it has been received by somebody.
+    while execute(&mut con, Command::Publish(channel, "one")).expect("executed")
+      == Response::Item(ResponseValue::Integer(0))
+    {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    execute(&mut con, Command::Publish(channel, "two")).expect("executed");
+    execute(&mut con, Command::Publish(channel, "three")).expect("executed");
+  });
+
+  let mut received = vec![];
+  let result = subscribe_with(subscriber, Arity::One(channel), |message: Message| {
+    received.push(message.payload);
+
+    if received.len() == 3 {
+      std::ops::ControlFlow::Break(received.len())
+    } else {
+      std::ops::ControlFlow::Continue(())
+    }
+  })
+  .expect("subscribed");
+
+  publisher.join().expect("published");
+  assert_eq!(result, 3);
+  assert_eq!(received, vec!["one", "two", "three"]);
+}
+
+#[test]
+fn test_incr_float() {
+  let key = "test_incr_float";
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(
+    &mut con,
+    StringCommand::Set(Arity::One((key, "10.50")), None, Insertion::Always),
+  )
+  .expect("executed");
+  let up = incr_float(&mut con, key, 0.1).expect("executed");
+  let down = incr_float(&mut con, key, -5.0).expect("executed");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  assert_eq!((up, down), (10.6, 5.6));
+}
+
+#[test]
+fn test_hincr_float() {
+  let key = "test_hincr_float";
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(
+    &mut con,
+    HashCommand::Set(key, Arity::One(("field", "10.50")), Insertion::Always),
+  )
+  .expect("executed");
+  let up = hincr_float(&mut con, key, "field", 0.1).expect("executed");
+  let down = hincr_float(&mut con, key, "field", -5.0).expect("executed");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  assert_eq!((up, down), (10.6, 5.6));
+}
+
+#[test]
+fn test_get_and_extend() {
+  let key = "test_get_and_extend";
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(
+    &mut con,
+    StringCommand::Set(Arity::One((key, "kramer")), None, Insertion::Always),
+  )
+  .expect("executed");
+  let mut support = None;
+  let result = get_and_extend(&mut con, key, std::time::Duration::from_millis(1500), &mut support).expect("executed");
+  // There is no TTL command enum; PTTL is written out by hand.
+  let pttl = execute(&mut con, "*2\r\n$4\r\nPTTL\r\n$19\r\ntest_get_and_extend\r\n").expect("executed");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  assert_eq!(support, Some(true));
+  assert_eq!(result, Response::Item(ResponseValue::String("kramer".into())));
+  assert!(
+    matches!(pttl, Response::Item(ResponseValue::Integer(ms)) if ms > 0 && ms <= 1500),
+    "unexpected PTTL response {:?}",
+    pttl
+  );
+}
+
+#[test]
+fn test_get_and_extend_modern_mock() {
+  let (addr, server) = mock_server(|args| match args[0].as_str() {
+    "COMMAND" => "*1\r\n*3\r\n$5\r\ngetex\r\n:-2\r\n*1\r\n+write\r\n",
+    _ => "$6\r\nkramer\r\n",
+  });
+  let mut support = None;
+  let ttl = std::time::Duration::from_millis(1500);
+  let results = {
+    let mut con = std::net::TcpStream::connect(addr).expect("connection");
+    let first = get_and_extend(&mut con, "seinfeld", ttl, &mut support).expect("executed");
+    let second = get_and_extend(&mut con, "seinfeld", ttl, &mut support).expect("executed");
+    (first, second)
+  };
+  let commands = server.join().expect("served");
+  let value = || Response::Item(ResponseValue::String("kramer".into()));
+  assert_eq!(support, Some(true));
+  assert_eq!(results, (value(), value()));
+  assert_eq!(
+    commands,
+    vec!["COMMAND INFO GETEX", "GETEX seinfeld PX 1500", "GETEX seinfeld PX 1500"]
+  );
+}
+
+#[test]
+fn test_get_and_extend_fallback_mock() {
+  let (addr, server) = mock_server(|args| match args[0].as_str() {
+    "COMMAND" => "*1\r\n*-1\r\n",
+    "MULTI" => "+OK\r\n",
+    "EXEC" => "*2\r\n$6\r\nkramer\r\n:1\r\n",
+    _ => "+QUEUED\r\n",
+  });
+  let mut support = None;
+  let ttl = std::time::Duration::from_millis(1500);
+  let result = {
+    let mut con = std::net::TcpStream::connect(addr).expect("connection");
+    get_and_extend(&mut con, "seinfeld", ttl, &mut support).expect("executed")
+  };
+  let commands = server.join().expect("served");
+  assert_eq!(support, Some(false));
+  assert_eq!(result, Response::Item(ResponseValue::String("kramer".into())));
+  assert_eq!(
+    commands,
+    vec![
+      "COMMAND INFO GETEX",
+      "MULTI",
+      "GET seinfeld",
+      "PEXPIRE seinfeld 1500",
+      "EXEC"
+    ]
+  );
+}
+
+#[test]
+fn test_get_and_extend_fallback_wrongtype_mock() {
+  let (addr, server) = mock_server(|args| match args[0].as_str() {
+    "COMMAND" => "*1\r\n*-1\r\n",
+    "MULTI" => "+OK\r\n",
+    "EXEC" => "*2\r\n-WRONGTYPE Operation against a key holding the wrong kind of value\r\n:1\r\n",
+    _ => "+QUEUED\r\n",
+  });
+  let ttl = std::time::Duration::from_millis(1500);
+  let result = {
+    let mut con = std::net::TcpStream::connect(addr).expect("connection");
+    get_and_extend(&mut con, "seinfeld", ttl, &mut None)
+  };
+  let commands = server.join().expect("served");
+  let error = result.expect_err("failed");
+  assert_eq!(error.kind(), std::io::ErrorKind::Other);
+  assert!(error.to_string().contains("WRONGTYPE"));
+  assert_eq!(
+    commands,
+    vec![
+      "COMMAND INFO GETEX",
+      "MULTI",
+      "GET seinfeld",
+      "PEXPIRE seinfeld 1500",
+      "EXEC"
+    ]
+  );
+}
+
+#[test]
+fn test_get_and_extend_empty_value_mock() {
+  let modern = "*1\r\n*3\r\n$5\r\ngetex\r\n:-2\r\n*1\r\n+write\r\n";
+  let results = [modern, "*1\r\n*-1\r\n"]
+    .iter()
+    .map(|info| {
+      let info = *info;
+      let (addr, server) = mock_server(move |args| match args[0].as_str() {
+        "COMMAND" => info,
+        "GETEX" => "$0\r\n\r\n",
+        "MULTI" => "+OK\r\n",
+        "EXEC" => "*2\r\n$0\r\n\r\n:1\r\n",
+        _ => "+QUEUED\r\n",
+      });
+      let result = {
+        let mut con = std::net::TcpStream::connect(addr).expect("connection");
+        let ttl = std::time::Duration::from_millis(1500);
+        get_and_extend(&mut con, "seinfeld", ttl, &mut None).expect("executed")
+      };
+      server.join().expect("served");
+      result
+    })
+    .collect::<Vec<Response>>();
+  let empty = || Response::Item(ResponseValue::String(String::new()));
+  assert_eq!(results, vec![empty(), empty()]);
+}
+
+#[test]
+fn test_get_and_extend_rejects_sub_millisecond_ttl() {
+  let mut con = std::io::Cursor::new(Vec::new());
+  let ttl = std::time::Duration::from_micros(500);
+  let error = get_and_extend(&mut con, "seinfeld", ttl, &mut Some(false)).expect_err("rejected");
+  assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+  assert!(con.into_inner().is_empty());
+}
+
+#[test]
+fn test_push_order_left() {
+  let key = "test_push_order_left";
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  let push = ListCommand::Push((Side::Left, Insertion::Always), key, Arity::Many(vec!["a", "b", "c"]));
+  execute(&mut con, push).expect("executed");
+  let result = execute(&mut con, ListCommand::Range::<_, &str>(key, 0, -1)).expect("executed");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  assert_eq!(
+    result,
+    Response::Array(vec![
+      ResponseValue::String(String::from("c")),
+      ResponseValue::String(String::from("b")),
+      ResponseValue::String(String::from("a")),
+    ])
+  );
+}
+
+#[test]
+fn test_push_order_right() {
+  let key = "test_push_order_right";
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  let push = ListCommand::Push((Side::Right, Insertion::Always), key, Arity::Many(vec!["a", "b", "c"]));
+  execute(&mut con, push).expect("executed");
+  let result = execute(&mut con, ListCommand::Range::<_, &str>(key, 0, -1)).expect("executed");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  assert_eq!(
+    result,
+    Response::Array(vec![
+      ResponseValue::String(String::from("a")),
+      ResponseValue::String(String::from("b")),
+      ResponseValue::String(String::from("c")),
+    ])
+  );
+}
+
+#[test]
+fn test_xautoclaim_idle() {
+  let (key, group) = ("test_xautoclaim_idle", "test_xautoclaim_idle_group");
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  execute(
+    &mut con,
+    StreamCommand::CreateGroup::<_, &str>(key, group, StreamId::Id(0, 0)),
+  )
+  .expect("executed");
+  let id = execute(&mut con, StreamCommand::Add(key, None, Arity::One(("name", "kramer")))).expect("executed");
+  let read = StreamCommand::ReadGroup::<_, &str> {
+    key,
+    group,
+    consumer: "jerry",
+    count: Some(1),
+  };
+  execute(&mut con, read).expect("executed");
+  std::thread::sleep(std::time::Duration::from_millis(50));
+  let claim = StreamCommand::AutoClaim::<_, &str> {
+    key,
+    group,
+    consumer: "elaine",
+    min_idle: 10,
+    start: StreamId::Id(0, 0),
+    count: Some(10),
+  };
+  let result = execute(&mut con, claim).expect("executed");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+
+  let id = match id {
+    Response::Item(ResponseValue::String(id)) => id,
+    other => panic!("unexpected XADD response {:?}", other),
+  };
+  let mut parts = match result {
+    Response::Array(parts) => parts.into_iter(),
+    other => panic!("unexpected XAUTOCLAIM response {:?}", other),
+  };
+
+  // Redis 7 includes a third element listing deleted entries.
+  assert_eq!(parts.next(), Some(ResponseValue::String("0-0".into())));
+  assert_eq!(
+    parts.next(),
+    Some(ResponseValue::Array(vec![ResponseValue::Array(vec![
+      ResponseValue::String(id),
+      ResponseValue::Array(vec![
+        ResponseValue::String("name".into()),
+        ResponseValue::String("kramer".into()),
+      ]),
+    ])]))
+  );
+}
+
+#[test]
+fn test_xpending_summary() {
+  let (key, group) = ("test_xpending_summary", "test_xpending_summary_group");
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  execute(
+    &mut con,
+    StreamCommand::CreateGroup::<_, &str>(key, group, StreamId::Id(0, 0)),
+  )
+  .expect("executed");
+  let id = execute(&mut con, StreamCommand::Add(key, None, Arity::One(("name", "kramer")))).expect("executed");
+  let read = StreamCommand::ReadGroup::<_, &str> {
+    key,
+    group,
+    consumer: "jerry",
+    count: Some(1),
+  };
+  execute(&mut con, read).expect("executed");
+  let pending = StreamCommand::Pending::<_, &str> {
+    key,
+    group,
+    range: None,
+    consumer: None,
+  };
+  let result = execute(&mut con, pending).expect("executed");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+
+  let id = match id {
+    Response::Item(ResponseValue::String(id)) => id,
+    other => panic!("unexpected XADD response {:?}", other),
+  };
+  assert_eq!(
+    result,
+    Response::Array(vec![
+      ResponseValue::Integer(1),
+      ResponseValue::String(id.clone()),
+      ResponseValue::String(id),
+      ResponseValue::Array(vec![ResponseValue::Array(vec![
+        ResponseValue::String("jerry".into()),
+        ResponseValue::String("1".into()),
+      ])]),
+    ])
+  );
+}
+
+#[test]
+fn test_execute_cluster_moved_mock() {
+  let (right, right_server) = mock_server(|_| "$6\r\nkramer\r\n");
+  let moved = format!("-MOVED {} {}\r\n", key_slot("seinfeld"), right);
+  let (wrong, wrong_server) = mock_server(move |_| moved.clone());
+  let mut nodes = ClusterNodes::new(vec![wrong.clone(), right.clone()]);
+  let result = execute_cluster(&mut nodes, StringCommand::Get::<_, &str>(Arity::One("seinfeld"))).expect("executed");
+  assert_eq!(result, Response::Item(ResponseValue::String("kramer".into())));
+  assert_eq!(nodes.node_for(key_slot("seinfeld")), Some(right.as_str()));
+  assert_eq!(wrong_server.join().expect("served").len(), 1);
+  assert_eq!(right_server.join().expect("served").len(), 1);
+}
+
+// Requires a running cluster; provide the comma separated addresses of its nodes through
+// `REDIS_CLUSTER_NODES`.
+#[test]
+#[ignore]
+fn test_execute_cluster_follows_moved() {
+  let key = "test_execute_cluster_follows_moved";
+  let addrs = var("REDIS_CLUSTER_NODES")
+    .expect("cluster nodes")
+    .split(',')
+    .map(String::from)
+    .collect::<Vec<String>>();
+  let mut redirected = false;
+
+  // Every node is tried as the initial target; at least one of them does not serve the slot.
+  for first in 0..addrs.len() {
+    let mut ordered = addrs.clone();
+    ordered.rotate_left(first);
+    let mut nodes = ClusterNodes::new(ordered.clone());
+    let set = StringCommand::Set(Arity::One((key, "kramer")), None, Insertion::Always);
+    execute_cluster(&mut nodes, set).expect("executed");
+    let result = execute_cluster(&mut nodes, StringCommand::Get::<_, &str>(Arity::One(key))).expect("executed");
+    execute_cluster(&mut nodes, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+    assert_eq!(result, Response::Item(ResponseValue::String("kramer".into())));
+    redirected = redirected || nodes.node_for(key_slot(key)) != Some(ordered[0].as_str());
+  }
+
+  assert!(redirected);
+}
+
+#[test]
+fn test_lpos_rank() {
+  let key = "test_lpos_rank";
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  let push = ListCommand::Push(
+    (Side::Right, Insertion::Always),
+    key,
+    Arity::Many(vec!["kramer", "jerry", "kramer", "elaine", "kramer", "george"]),
+  );
+  execute(&mut con, push).expect("executed");
+  let second = execute(&mut con, ListCommand::Pos(key, "kramer", Some(2), None)).expect("executed");
+  let last = execute(&mut con, ListCommand::Pos(key, "kramer", Some(-1), None)).expect("executed");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  assert_eq!(second, Response::Item(ResponseValue::Integer(2)));
+  assert_eq!(last, Response::Item(ResponseValue::Integer(4)));
+}
+
+#[test]
+fn test_drain_list_right() {
+  let key = "test_drain_list_right";
+  let mut con = std::net::TcpStream::connect(get_redis_url()).expect("connection");
+  execute(&mut con, Command::Del::<_, &str>(Arity::One(key))).expect("executed");
+  let push = ListCommand::Push(
+    (Side::Right, Insertion::Always),
+    key,
+    Arity::Many(vec!["kramer", "jerry", "elaine", "george"]),
+  );
+  execute(&mut con, push).expect("executed");
+  let drained = drain_list(&mut con, key, Side::Right, &mut None).expect("executed");
+  let exists = execute(&mut con, Command::Exists::<_, &str>(Arity::One(key))).expect("executed");
+  assert_eq!(drained, vec!["george", "elaine", "jerry", "kramer"]);
+  assert_eq!(exists, Response::Item(ResponseValue::Integer(0)));
+}
+
+#[test]
+fn test_drain_list_fallback_mock() {
+  let remaining = std::sync::Mutex::new(vec!["kramer", "", "jerry"]);
+  let (addr, server) = mock_server(move |args| {
+    if args[0] == "COMMAND" {
+      return String::from("*1\r\n*2\r\n$4\r\nrpop\r\n:2\r\n");
+    }
+
+    match remaining.lock().expect("lock").pop() {
+      Some(item) => format!("${}\r\n{}\r\n", item.len(), item),
+      None => String::from("$-1\r\n"),
+    }
+  });
+  let mut counted = None;
+  let drained = {
+    let mut con = std::net::TcpStream::connect(addr).expect("connection");
+    drain_list(&mut con, "seinfeld", Side::Right, &mut counted).expect("executed")
+  };
+  let commands = server.join().expect("served");
+  assert_eq!(counted, Some(false));
+  assert_eq!(drained, vec!["jerry", "", "kramer"]);
+  assert_eq!(
+    commands,
+    vec![
+      "COMMAND INFO RPOP",
+      "RPOP seinfeld",
+      "RPOP seinfeld",
+      "RPOP seinfeld",
+      "RPOP seinfeld"
+    ]
+  );
+}
+
+#[test]
+fn test_connection_builder_nodelay() {
+  let mut con = ConnectionBuilder::new(get_redis_url())
+    .nodelay(true)
+    .keepalive(Some(std::time::Duration::from_secs(60)))
+    .connect()
+    .expect("connection");
+  assert!(con.nodelay().expect("nodelay"));
+  let result = execute(&mut con, Command::Echo::<_, &str>("hello")).expect("executed");
+  assert_eq!(result, Response::Item(ResponseValue::String("hello".into())));
+}
+
+#[test]
+fn test_connection_builder_nodelay_mock() {
+  let (addr, server) = mock_server(|args| format!("${}\r\n{}\r\n", args[1].len(), args[1]));
+  let result = {
+    let mut con = ConnectionBuilder::new(addr)
+      .nodelay(true)
+      .keepalive(Some(std::time::Duration::from_secs(60)))
+      .connect()
+      .expect("connection");
+    assert!(con.nodelay().expect("nodelay"));
+    execute(&mut con, Command::Echo::<_, &str>("hello")).expect("executed")
+  };
+  assert_eq!(result, Response::Item(ResponseValue::String("hello".into())));
+  assert_eq!(server.join().expect("served"), vec!["ECHO hello"]);
+}
+
+#[test]
+fn test_read_empty_bulk_string() {
+  let result = read(&b"$0\r\n\r\n"[..]).expect("read");
+  assert_eq!(result, Response::Item(ResponseValue::String(String::new())));
+}
+
+#[test]
+fn test_read_tagged_simple_and_bulk() {
+  let simple = read_tagged(&b"+OK\r\n"[..]).expect("read");
+  let bulk = read_tagged(&b"$2\r\nOK\r\n"[..]).expect("read");
+  assert_eq!(
+    simple,
+    ("simple-string", Response::Item(ResponseValue::String("OK".into())))
+  );
+  assert_eq!(
+    bulk,
+    ("bulk-string", Response::Item(ResponseValue::String("OK".into())))
+  );
+}